@@ -0,0 +1,146 @@
+//! Capture buffer benchmark: the lock-free `RingBuffer` against the
+//! `Mutex<VecDeque<f32>>` it replaced.
+//!
+//! Run with `cargo bench --bench ring_buffer`. Measures how long the audio
+//! callback spends writing a block (alone, and while another thread keeps
+//! snapshotting the buffer like the transcription worker and meter do), and
+//! how long a full 45s snapshot takes.
+
+use kuroko_lib::ring_buffer::RingBuffer;
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_SAMPLES: usize = 16000 * 45;
+// One 10ms cpal callback after resampling to 16 kHz
+const BLOCK: usize = 160;
+const WRITES: usize = 200_000;
+const SNAPSHOTS: usize = 200;
+
+trait CaptureBuffer: Send + Sync {
+    fn write(&self, input: &[f32]);
+    fn snapshot(&self) -> Vec<f32>;
+}
+
+/// The previous implementation, kept here as the baseline.
+struct DequeBuffer(Mutex<VecDeque<f32>>);
+
+impl CaptureBuffer for DequeBuffer {
+    fn write(&self, input: &[f32]) {
+        let mut guard = self.0.lock().unwrap();
+        for &val in input {
+            guard.push_back(val);
+            if guard.len() > MAX_SAMPLES {
+                guard.pop_front();
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<f32> {
+        let guard = self.0.lock().unwrap();
+        guard.iter().cloned().collect()
+    }
+}
+
+impl CaptureBuffer for RingBuffer {
+    fn write(&self, input: &[f32]) {
+        self.push_slice(input);
+    }
+
+    fn snapshot(&self) -> Vec<f32> {
+        RingBuffer::snapshot(self)
+    }
+}
+
+type MakeBuffer = fn() -> Arc<dyn CaptureBuffer>;
+
+struct Stats {
+    mean: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+fn stats(mut samples: Vec<Duration>) -> Stats {
+    samples.sort();
+    let total: Duration = samples.iter().sum();
+    Stats {
+        mean: total / samples.len() as u32,
+        p99: samples[samples.len() * 99 / 100],
+        max: *samples.last().unwrap(),
+    }
+}
+
+fn report(label: &str, s: &Stats) {
+    println!(
+        "  {:<28} mean {:>10.2?}  p99 {:>10.2?}  max {:>10.2?}",
+        label, s.mean, s.p99, s.max
+    );
+}
+
+fn fill(buffer: &dyn CaptureBuffer) {
+    let block = vec![0.1f32; BLOCK];
+    for _ in 0..MAX_SAMPLES / BLOCK + 1 {
+        buffer.write(&block);
+    }
+}
+
+fn bench_writes(buffer: Arc<dyn CaptureBuffer>, contended: bool) -> Stats {
+    fill(buffer.as_ref());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let reader = contended.then(|| {
+        let buffer = buffer.clone();
+        let stop = stop.clone();
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                black_box(buffer.snapshot());
+            }
+        })
+    });
+
+    let block: Vec<f32> = (0..BLOCK).map(|i| (i as f32 * 0.01).sin()).collect();
+    let mut timings = Vec::with_capacity(WRITES);
+    for _ in 0..WRITES {
+        let start = Instant::now();
+        buffer.write(black_box(&block));
+        timings.push(start.elapsed());
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    if let Some(reader) = reader {
+        reader.join().unwrap();
+    }
+    stats(timings)
+}
+
+fn bench_snapshots(buffer: &dyn CaptureBuffer) -> Stats {
+    fill(buffer);
+    let timings = (0..SNAPSHOTS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(buffer.snapshot());
+            start.elapsed()
+        })
+        .collect();
+    stats(timings)
+}
+
+fn main() {
+    let candidates: [(&str, MakeBuffer); 2] = [
+        ("Mutex<VecDeque<f32>>", || {
+            Arc::new(DequeBuffer(Mutex::new(VecDeque::with_capacity(
+                MAX_SAMPLES,
+            ))))
+        }),
+        ("RingBuffer", || Arc::new(RingBuffer::new(MAX_SAMPLES))),
+    ];
+
+    for (name, make) in candidates {
+        println!("{}", name);
+        report("callback write", &bench_writes(make(), false));
+        report("callback write (contended)", &bench_writes(make(), true));
+        report("snapshot 45s", &bench_snapshots(make().as_ref()));
+    }
+}
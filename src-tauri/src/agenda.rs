@@ -1,10 +1,41 @@
+use crate::audio::now_ms;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Room for a batch of goals and a full transcript buffer; Ollama silently
+// drops the start of prompts longer than its context
+const NUM_CTX: u32 = 8192;
+
+/// How long one scoring request may take; a local model working through a
+/// full batch on the CPU needs most of it.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// Covers Ollama loading the embedding model on first use
+const EMBEDDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Tries per scoring request, and the wait before the first retry; every
+// further retry waits twice as long
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
 
 #[derive(Serialize)]
 pub struct OllamaRequest {
     pub model: String,
     pub prompt: String,
     pub stream: bool,
+    /// JSON schema the reply is constrained to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<OllamaOptions>,
+}
+
+#[derive(Serialize)]
+pub struct OllamaOptions {
+    pub temperature: f32,
+    pub num_ctx: u32,
 }
 
 #[derive(Deserialize)]
@@ -12,27 +43,110 @@ pub struct OllamaResponse {
     pub response: String,
 }
 
+/// Where an agenda item stands in the meeting.
+///
+/// ```text
+/// pending ──> in_progress ──> answered ──> reopened
+///    │             │                          │
+///    └─────────────┴──> skipped / deferred ───┘
+/// ```
+///
+/// A reopened item moves on like a pending one.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AgendaStatus {
+    #[default]
+    Pending,
+    /// Partly covered. Stored as "captured" before this was an enum.
+    #[serde(alias = "captured")]
+    InProgress,
+    Answered,
+    Skipped,
+    Deferred,
+    Reopened,
+}
+
+impl AgendaStatus {
+    pub fn can_transition_to(self, next: AgendaStatus) -> bool {
+        use AgendaStatus::*;
+        matches!(
+            (self, next),
+            (
+                Pending | Reopened,
+                InProgress | Answered | Skipped | Deferred
+            ) | (InProgress, Answered | Skipped | Deferred)
+                | (Answered | Skipped | Deferred, Reopened)
+        )
+    }
+
+    /// Settled items are left out of scoring until reopened.
+    pub fn is_settled(self) -> bool {
+        matches!(
+            self,
+            AgendaStatus::Answered | AgendaStatus::Skipped | AgendaStatus::Deferred
+        )
+    }
+}
+
+/// One step in an agenda item's history.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusChange {
+    pub from: AgendaStatus,
+    pub to: AgendaStatus,
+    /// Milliseconds since the Unix epoch
+    pub at_ms: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AgendaItem {
     pub id: String,
     pub text: String,
-    pub status: String,         // "pending", "answered"
+    #[serde(default)]
+    pub status: AgendaStatus,
     pub answer: Option<String>, // Latest answer/summary
     pub score: f32,             // 0.0 to 1.0
     pub evidence: Vec<String>,  // Accumulative evidence
+    /// Every status change, oldest first
+    #[serde(default)]
+    pub history: Vec<StatusChange>,
     #[serde(skip)]
     pub embedding: Option<Vec<f32>>,
 }
 
+impl AgendaItem {
+    /// Moves the item to `next` and records the change. Staying put is not a
+    /// change; a move the state machine does not allow is an error.
+    pub fn transition(&mut self, next: AgendaStatus, at_ms: u64) -> Result<(), String> {
+        if next == self.status {
+            return Ok(());
+        }
+        if !self.status.can_transition_to(next) {
+            return Err(format!(
+                "Agenda item '{}' cannot go from {:?} to {:?}",
+                self.id, self.status, next
+            ));
+        }
+        self.history.push(StatusChange {
+            from: self.status,
+            to: next,
+            at_ms,
+        });
+        self.status = next;
+        Ok(())
+    }
+}
+
+/// A request to Ollama's `/api/embed`, which embeds several inputs at once.
 #[derive(Serialize)]
-pub struct OllamaEmbeddingRequest {
+pub struct OllamaEmbedRequest {
     pub model: String,
-    pub prompt: String,
+    pub input: Vec<String>,
 }
 
 #[derive(Deserialize)]
-pub struct OllamaEmbeddingResponse {
-    pub embedding: Vec<f32>,
+pub struct OllamaEmbedResponse {
+    /// One vector per input, in order
+    pub embeddings: Vec<Vec<f32>>,
 }
 
 #[derive(Deserialize)]
@@ -43,26 +157,108 @@ struct ScoreResponse {
     new_evidence: Option<String>,
 }
 
+impl ScoreResponse {
+    /// Clamps the score into [0, 1]; a score that is not a number is an error.
+    fn validated(mut self) -> Result<Self, String> {
+        if !self.score.is_finite() {
+            return Err(format!("score {} is not a number", self.score));
+        }
+        self.score = self.score.clamp(0.0, 1.0);
+        Ok(self)
+    }
+}
+
+/// How agenda items are scored against the transcript.
+#[derive(Debug, Clone)]
+pub struct ScoringOptions {
+    pub model: String,
+    pub embedding_model: Option<String>,
+    /// Items less similar than this to the transcript are not sent to the LLM
+    pub similarity_threshold: f32,
+    pub base_url: String,
+    /// Score at which an item counts as answered
+    pub answered_threshold: f32,
+    /// Most items scored in one prompt
+    pub batch_size: usize,
+    /// For each request; every retry gets its own
+    pub timeout: Duration,
+}
+
+/// Lets a scoring pass notice that the agenda it is working on was replaced.
+///
+/// Taken from a generation counter that is bumped on every agenda change; the
+/// token is cancelled once the counter moves on.
+///
+/// The token is checked before each request and retry, not during one: a
+/// request already sent runs until Ollama answers or the client timeout ends it.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    generation: Arc<AtomicU64>,
+    started: u64,
+}
+
+impl CancelToken {
+    pub fn new(generation: Arc<AtomicU64>) -> Self {
+        let started = generation.load(Ordering::SeqCst);
+        CancelToken {
+            generation,
+            started,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.generation.load(Ordering::SeqCst) != self.started
+    }
+}
+
+/// What a scoring pass changed, and which replies could not be used.
+#[derive(Debug, Default)]
+pub struct ScoringReport {
+    /// Ids of the items that changed
+    pub updated: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// One entry of a batched scoring reply.
+#[derive(Deserialize)]
+struct BatchScore {
+    id: String,
+    #[serde(flatten)]
+    scored: ScoreResponse,
+}
+
 pub fn get_embedding(model: &str, text: &str, base_url: &str) -> Result<Vec<f32>, String> {
-    let client = reqwest::blocking::Client::new();
-    let req = OllamaEmbeddingRequest {
+    let mut embeddings = embed(model, &[text.to_string()], base_url)?;
+    Ok(embeddings.remove(0))
+}
+
+/// Embeds every input in a single request.
+pub fn embed(model: &str, inputs: &[String], base_url: &str) -> Result<Vec<Vec<f32>>, String> {
+    let client = client(EMBEDDING_TIMEOUT)?;
+    let req = OllamaEmbedRequest {
         model: model.to_string(),
-        prompt: text.to_string(),
+        input: inputs.to_vec(),
     };
 
-    let url = format!("{}/api/embeddings", base_url.trim_end_matches('/'));
+    let url = format!("{}/api/embed", base_url.trim_end_matches('/'));
     let resp = client
         .post(url)
         .json(&req)
         .send()
         .map_err(|e| e.to_string())?;
 
-    if resp.status().is_success() {
-        let res: OllamaEmbeddingResponse = resp.json().map_err(|e| e.to_string())?;
-        Ok(res.embedding)
-    } else {
-        Err(format!("Ollama embedding failed: {}", resp.status()))
+    if !resp.status().is_success() {
+        return Err(format!("Ollama embedding failed: {}", resp.status()));
     }
+    let res: OllamaEmbedResponse = resp.json().map_err(|e| e.to_string())?;
+    if res.embeddings.len() != inputs.len() {
+        return Err(format!(
+            "Ollama returned {} embeddings for {} inputs",
+            res.embeddings.len(),
+            inputs.len()
+        ));
+    }
+    Ok(res.embeddings)
 }
 
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
@@ -78,52 +274,286 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     dot_product / (norm_a * norm_b)
 }
 
+/// Scores the unsettled agenda items against the latest transcript.
+///
+/// Items the transcript embedding is not similar enough to are skipped. The
+/// rest are scored up to `batch_size` at a time in a single prompt; a batch
+/// whose reply cannot be parsed, and any item the reply leaves out, is scored
+/// on its own instead. A `batch_size` of 0 or 1 scores every item on its own.
+/// Replies that fail to parse are listed in the report rather than dropped.
+/// A request Ollama fails outright, even after retries, ends the pass: the
+/// calls after it would only wait out the same timeouts.
+///
+/// Meant to run on a snapshot of the agenda, see `merge_scored`. Once `cancel`
+/// fires no further requests are made and the report is incomplete.
 pub fn score_agenda_items(
-    model: &str,
     text: &str,
     items: &mut [AgendaItem],
-    embedding_model: Option<&str>,
-    similarity_threshold: f32,
-    base_url: &str,
-    answered_threshold: f32,
-) -> Vec<String> {
-    let mut updates = Vec::new();
+    options: &ScoringOptions,
+    cancel: &CancelToken,
+) -> ScoringReport {
+    let mut report = ScoringReport::default();
 
     // 1. Get embedding for current text if possible
-    let text_embedding = if let Some(emb_model) = embedding_model {
-        get_embedding(emb_model, text, base_url).ok()
+    let text_embedding = if let Some(emb_model) = &options.embedding_model {
+        get_embedding(emb_model, text, &options.base_url).ok()
     } else {
         None
     };
 
-    let client = reqwest::blocking::Client::new();
-
-    for item in items.iter_mut() {
-        if item.status == "answered" && item.score >= answered_threshold {
+    // 2. Filter by similarity if embeddings available
+    let mut candidates = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        if item.status.is_settled() {
             continue;
         }
-
-        // 2. Filter by similarity if embeddings available
         if let (Some(text_emb), Some(item_emb)) = (&text_embedding, &item.embedding) {
             let sim = cosine_similarity(text_emb, item_emb);
             // Threshold can be tuned. 0.4 is usually decent for simple overlap in some models,
             // but for "instruction" tuned embeddings it varies.
             // Let's use a conservative threshold to avoid missing things, or just skip if very low.
-            if sim < similarity_threshold {
+            if sim < options.similarity_threshold {
                 continue;
             }
             println!("[Agenda] Similarity for '{}': {:.4}", item.text, sim);
         }
+        candidates.push(index);
+    }
+
+    // 3. Score the candidates, several per prompt when batching
+    let client = match client(options.timeout) {
+        Ok(client) => client,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+
+    for batch in candidates.chunks(options.batch_size.max(1)) {
+        if cancel.is_cancelled() {
+            return report;
+        }
+        let mut unscored = batch.to_vec();
+
+        if batch.len() > 1 {
+            let batch_items: Vec<&AgendaItem> = batch.iter().map(|&i| &items[i]).collect();
+            let prompt = batch_prompt(&batch_items, text);
+            let response = match generate(&client, options, prompt, batch_schema(), cancel) {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("[Agenda] Batch request failed: {}", e);
+                    report
+                        .errors
+                        .push(format!("Batch of {} goals: {}", batch.len(), e));
+                    return report;
+                }
+            };
 
-        // 3. Prepare Accumulative Prompt
-        let evidence_text = if item.evidence.is_empty() {
-            "None".to_string()
-        } else {
-            item.evidence.join("\n- ")
+            match parse_batch(&response) {
+                Ok(scores) => {
+                    for score in scores {
+                        let Some(pos) = unscored.iter().position(|&i| items[i].id == score.id)
+                        else {
+                            continue;
+                        };
+                        // An unusable entry leaves the item to be scored on its own
+                        match score.scored.validated() {
+                            Ok(scored) => {
+                                let item = &mut items[unscored.remove(pos)];
+                                if apply_score(item, scored, options.answered_threshold) {
+                                    report.updated.push(item.id.clone());
+                                }
+                            }
+                            Err(e) => report
+                                .errors
+                                .push(format!("'{}': {}", items[unscored[pos]].text, e)),
+                        }
+                    }
+                    if !unscored.is_empty() {
+                        println!(
+                            "[Agenda] Batch reply skipped {} goals, scoring them one by one.",
+                            unscored.len()
+                        );
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[Agenda] Batch reply failed ({}), scoring {} goals one by one.",
+                        e,
+                        batch.len()
+                    );
+                    report
+                        .errors
+                        .push(format!("Batch of {} goals: {}", batch.len(), e));
+                }
+            }
+        }
+
+        for index in unscored {
+            if cancel.is_cancelled() {
+                return report;
+            }
+            let item = &mut items[index];
+            let prompt = item_prompt(item, text);
+            let response = match generate(&client, options, prompt, score_schema(), cancel) {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("[Agenda] Scoring '{}' failed: {}", item.text, e);
+                    report.errors.push(format!("'{}': {}", item.text, e));
+                    return report;
+                }
+            };
+            let scored = parse_score(&response).and_then(ScoreResponse::validated);
+            match scored {
+                Ok(scored) => {
+                    if apply_score(item, scored, options.answered_threshold) {
+                        report.updated.push(item.id.clone());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[Agenda] Scoring '{}' failed: {}", item.text, e);
+                    report.errors.push(format!("'{}': {}", item.text, e));
+                }
+            }
+        }
+    }
+    report
+}
+
+fn generate(
+    client: &reqwest::blocking::Client,
+    options: &ScoringOptions,
+    prompt: String,
+    schema: serde_json::Value,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let req = OllamaRequest {
+        model: options.model.clone(),
+        prompt,
+        stream: false,
+        format: Some(schema),
+        options: Some(OllamaOptions {
+            temperature: 0.0,
+            num_ctx: NUM_CTX,
+        }),
+    };
+
+    let url = format!("{}/api/generate", options.base_url.trim_end_matches('/'));
+    let resp = post_with_retry(client, &url, &req, cancel)?;
+    let ollama_resp: OllamaResponse = resp.json().map_err(|e| e.to_string())?;
+    Ok(ollama_resp.response)
+}
+
+fn client(timeout: Duration) -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// Retries timeouts, dropped connections and server errors with exponential
+// backoff; any other failure is final
+fn post_with_retry<T: Serialize>(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    body: &T,
+    cancel: &CancelToken,
+) -> Result<reqwest::blocking::Response, String> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let error = match client.post(url).json(body).send() {
+            Ok(resp) if resp.status().is_success() => return Ok(resp),
+            Ok(resp)
+                if resp.status().is_server_error()
+                    || resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                format!("Ollama returned {}", resp.status())
+            }
+            Ok(resp) => return Err(format!("Ollama returned {}", resp.status())),
+            Err(e) if e.is_timeout() => "Ollama timed out".to_string(),
+            Err(e) => e.to_string(),
         };
+        if attempt == MAX_ATTEMPTS || cancel.is_cancelled() {
+            return Err(error);
+        }
+        eprintln!(
+            "[Agenda] {} (attempt {}/{}), retrying in {:?}",
+            error, attempt, MAX_ATTEMPTS, backoff
+        );
+        std::thread::sleep(backoff);
+        if cancel.is_cancelled() {
+            return Err(error);
+        }
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Copies scored items from a snapshot back into the live agenda, matched by
+/// id, and returns the ids that were copied.
+///
+/// Items removed since the snapshot was taken are dropped, and so are items
+/// whose status was changed by hand meanwhile: the hand-made change wins.
+pub fn merge_scored(
+    live: &mut [AgendaItem],
+    scored: &[AgendaItem],
+    updated: &[String],
+) -> Vec<String> {
+    let mut merged = Vec::new();
+    for id in updated {
+        let Some(scored) = scored.iter().find(|item| &item.id == id) else {
+            continue;
+        };
+        let Some(item) = live.iter_mut().find(|item| &item.id == id) else {
+            continue;
+        };
+        if !scored.history.starts_with(&item.history) {
+            continue;
+        }
+        item.status = scored.status;
+        item.history = scored.history.clone();
+        item.score = scored.score;
+        item.evidence = scored.evidence.clone();
+        item.answer = scored.answer.clone();
+        merged.push(id.clone());
+    }
+    merged
+}
 
-        let prompt = format!(
-            "You are a meeting assistant tracking a goal.
+// The reply Ollama is held to for a single goal
+fn score_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "match": { "type": "boolean" },
+            "score": { "type": "number", "minimum": 0, "maximum": 1 },
+            "new_evidence": { "type": "string" }
+        },
+        "required": ["match", "score", "new_evidence"]
+    })
+}
+
+// One single-goal reply per goal, tagged with the goal's id
+fn batch_schema() -> serde_json::Value {
+    let mut entry = score_schema();
+    entry["properties"]["id"] = json!({ "type": "string" });
+    entry["required"] = json!(["id", "match", "score", "new_evidence"]);
+    json!({ "type": "array", "items": entry })
+}
+
+fn evidence_text(item: &AgendaItem) -> String {
+    if item.evidence.is_empty() {
+        "None".to_string()
+    } else {
+        item.evidence.join("\n- ")
+    }
+}
+
+// Accumulative prompt for a single goal
+fn item_prompt(item: &AgendaItem, text: &str) -> String {
+    format!(
+        "You are a meeting assistant tracking a goal.
             Goal: \"{}\"
             
             Current Completion Score: {:.2} (0.0 to 1.0)
@@ -146,50 +576,521 @@ pub fn score_agenda_items(
                 \"score\": 0.5,
                 \"new_evidence\": \"Discussed budget cap.\"
             }}",
-            item.text, item.score, evidence_text, text
+        item.text,
+        item.score,
+        evidence_text(item),
+        text
+    )
+}
+
+// The same task for several goals at once, answered as one array
+fn batch_prompt(items: &[&AgendaItem], text: &str) -> String {
+    let goals: Vec<String> = items
+        .iter()
+        .map(|item| {
+            format!(
+                "Goal id: \"{}\"\nGoal: \"{}\"\nCurrent Completion Score: {:.2} (0.0 to 1.0)\nPrevious Evidence:\n- {}",
+                item.id,
+                item.text,
+                item.score,
+                evidence_text(item)
+            )
+        })
+        .collect();
+
+    format!(
+        "You are a meeting assistant tracking several goals.
+
+{}
+
+New Transcript Segment:
+\"{}\"
+
+Task, for EACH goal above:
+1. Analyze if the New Transcript Segment MATCHES the Goal.
+2. If it matches, does it provide NEW progress or information?
+3. Estimate the NEW TOTAL completion score (0.0 to 1.0) based on Previous Evidence + New Segment.
+4. Provide a one-sentence summary of the new evidence found (if any).
+
+Return a JSON array ONLY, with one object per goal id:
+[
+    {{
+        \"id\": \"goal id\",
+        \"match\": true/false,
+        \"score\": 0.5,
+        \"new_evidence\": \"Discussed budget cap.\"
+    }}
+]",
+        goals.join("\n\n"),
+        text
+    )
+}
+
+fn parse_score(response: &str) -> Result<ScoreResponse, String> {
+    serde_json::from_str(response).map_err(|e| format!("unreadable reply: {}", e))
+}
+
+fn parse_batch(response: &str) -> Result<Vec<BatchScore>, String> {
+    serde_json::from_str(response).map_err(|e| format!("unreadable reply: {}", e))
+}
+
+/// Records a score on the item; returns whether the item changed.
+fn apply_score(item: &mut AgendaItem, scored: ScoreResponse, answered_threshold: f32) -> bool {
+    if !scored.is_match {
+        return false;
+    }
+    if let Some(ev) = scored.new_evidence {
+        if !ev.is_empty() {
+            item.evidence.push(ev);
+        }
+    }
+    item.score = scored.score;
+    if item.score >= answered_threshold {
+        let _ = item.transition(AgendaStatus::Answered, now_ms());
+        item.answer = Some("Completed".to_string());
+    } else if item.score > 0.0 {
+        let _ = item.transition(AgendaStatus::InProgress, now_ms());
+        item.answer = Some(format!("In Progress ({:.0}%)", item.score * 100.0));
+    }
+    println!(
+        "[Agenda] Updated goal '{}' -> Score: {:.2}",
+        item.text, item.score
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AgendaStatus::*;
+
+    fn item() -> AgendaItem {
+        AgendaItem {
+            id: "1".to_string(),
+            text: "Agree on the Q3 budget".to_string(),
+            status: Pending,
+            answer: None,
+            score: 0.0,
+            evidence: Vec::new(),
+            history: Vec::new(),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn test_progresses_to_answered() {
+        let mut item = item();
+        item.transition(InProgress, 1_000).unwrap();
+        item.transition(InProgress, 2_000).unwrap();
+        item.transition(Answered, 3_000).unwrap();
+
+        assert_eq!(item.status, Answered);
+        assert_eq!(
+            item.history,
+            vec![
+                StatusChange {
+                    from: Pending,
+                    to: InProgress,
+                    at_ms: 1_000
+                },
+                StatusChange {
+                    from: InProgress,
+                    to: Answered,
+                    at_ms: 3_000
+                },
+            ]
         );
+    }
+
+    #[test]
+    fn test_pending_can_be_answered_skipped_or_deferred() {
+        for next in [Answered, Skipped, Deferred] {
+            let mut item = item();
+            item.transition(next, 1_000).unwrap();
+            assert_eq!(item.status, next);
+            assert!(item.status.is_settled());
+        }
+    }
+
+    #[test]
+    fn test_in_progress_can_be_skipped_or_deferred() {
+        for next in [Skipped, Deferred] {
+            let mut item = item();
+            item.transition(InProgress, 1_000).unwrap();
+            item.transition(next, 2_000).unwrap();
+            assert_eq!(item.status, next);
+        }
+    }
+
+    #[test]
+    fn test_settled_items_reopen_and_move_on() {
+        for settled in [Answered, Skipped, Deferred] {
+            let mut item = item();
+            item.transition(settled, 1_000).unwrap();
+            item.transition(Reopened, 2_000).unwrap();
+            assert!(!item.status.is_settled());
+            item.transition(InProgress, 3_000).unwrap();
+            item.transition(Answered, 4_000).unwrap();
+            assert_eq!(item.history.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_rejects_illegal_transitions() {
+        let mut item = item();
+        assert!(item.transition(Reopened, 1_000).is_err());
+        item.transition(InProgress, 1_000).unwrap();
+        assert!(item.transition(Pending, 2_000).is_err());
+        item.transition(Answered, 3_000).unwrap();
+        for next in [Pending, InProgress, Skipped, Deferred] {
+            assert!(item.transition(next, 4_000).is_err());
+        }
+        assert_eq!(item.status, Answered);
+        assert_eq!(item.history.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_batch_is_keyed_by_id() {
+        let response = r#"[
+  {"id": "2", "match": true, "score": 0.6, "new_evidence": "Hiring plan agreed."},
+  {"id": "1", "match": false, "score": 0.0, "new_evidence": null}
+]"#;
+        let scores = parse_batch(response).unwrap();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].id, "2");
+        assert!(scores[0].scored.is_match);
+        assert_eq!(scores[0].scored.score, 0.6);
+        assert_eq!(scores[1].id, "1");
+        assert!(!scores[1].scored.is_match);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_replies() {
+        assert!(parse_batch("I could not find any goals.").is_err());
+        assert!(parse_batch(r#"{"id": "1", "match": true, "score": 0.5}"#).is_err());
+        assert!(parse_batch(r#"[{"id": "1", "score": 0.5}]"#).is_err());
+        assert!(parse_score("Sure! {\"match\": true, \"score\": 0.3} Done.").is_err());
+        assert!(parse_score(r#"{"match": "yes", "score": 0.3}"#).is_err());
+    }
 
-        let req = OllamaRequest {
-            model: model.to_string(),
-            prompt,
-            stream: false,
+    #[test]
+    fn test_scores_are_clamped() {
+        let score = |score| ScoreResponse {
+            is_match: true,
+            score,
+            new_evidence: None,
         };
+        assert_eq!(score(1.4).validated().unwrap().score, 1.0);
+        assert_eq!(score(-0.2).validated().unwrap().score, 0.0);
+        assert_eq!(score(0.4).validated().unwrap().score, 0.4);
+        assert!(score(f32::INFINITY).validated().is_err());
+    }
 
-        let url = format!("{}/api/generate", base_url.trim_end_matches('/'));
-        if let Ok(resp) = client.post(url).json(&req).send() {
-            if let Ok(ollama_resp) = resp.json::<OllamaResponse>() {
-                let json_str = ollama_resp.response.trim();
-                if let Some(start) = json_str.find('{') {
-                    if let Some(end) = json_str.rfind('}') {
-                        let clean_json = &json_str[start..=end];
-
-                        if let Ok(scored) = serde_json::from_str::<ScoreResponse>(clean_json) {
-                            if scored.is_match {
-                                if let Some(ev) = scored.new_evidence {
-                                    if !ev.is_empty() {
-                                        item.evidence.push(ev);
-                                    }
-                                }
-                                item.score = scored.score;
-                                if item.score >= 1.0 {
-                                    item.status = "answered".to_string();
-                                    item.answer = Some("Completed".to_string());
-                                } else if item.score > 0.0 {
-                                    item.status = "captured".to_string(); // In progress
-                                    item.answer =
-                                        Some(format!("In Progress ({:.0}%)", item.score * 100.0));
-                                }
-                                updates.push(item.id.clone());
-                                println!(
-                                    "[Agenda] Updated goal '{}' -> Score: {:.2}",
-                                    item.text, item.score
-                                );
-                            }
-                        }
-                    }
-                }
-            }
+    #[test]
+    fn test_batch_prompt_lists_every_goal() {
+        let mut second = item();
+        second.id = "2".to_string();
+        second.text = "Pick a launch date".to_string();
+        second.evidence = vec!["Discussed May.".to_string()];
+        let first = item();
+
+        let prompt = batch_prompt(&[&first, &second], "We should launch in June.");
+        assert!(prompt.contains("Goal id: \"1\"\nGoal: \"Agree on the Q3 budget\""));
+        assert!(prompt.contains("Goal id: \"2\"\nGoal: \"Pick a launch date\""));
+        assert!(prompt.contains("- Discussed May."));
+        assert!(prompt.contains("\"We should launch in June.\""));
+    }
+
+    #[test]
+    fn test_apply_score_moves_item_along() {
+        let score = |is_match, score| ScoreResponse {
+            is_match,
+            score,
+            new_evidence: Some("Budget capped at 40k.".to_string()),
+        };
+        let mut item = item();
+
+        assert!(!apply_score(&mut item, score(false, 0.9), 0.95));
+        assert_eq!(item.status, Pending);
+        assert!(item.evidence.is_empty());
+
+        assert!(apply_score(&mut item, score(true, 0.5), 0.95));
+        assert_eq!(item.status, InProgress);
+        assert_eq!(item.answer.as_deref(), Some("In Progress (50%)"));
+
+        assert!(apply_score(&mut item, score(true, 0.97), 0.95));
+        assert_eq!(item.status, Answered);
+        assert_eq!(item.evidence.len(), 2);
+    }
+
+    #[test]
+    fn test_status_serde() {
+        assert_eq!(
+            serde_json::to_string(&InProgress).unwrap(),
+            "\"in_progress\""
+        );
+        let old: AgendaItem = serde_json::from_str(
+            r#"{"id":"2","text":"Hiring","status":"captured","answer":null,"score":0.4,"evidence":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(old.status, InProgress);
+        assert!(old.history.is_empty());
+    }
+
+    fn options(base_url: &str, batch_size: usize) -> ScoringOptions {
+        ScoringOptions {
+            model: "llama3.1:8b".to_string(),
+            embedding_model: None,
+            similarity_threshold: 0.35,
+            base_url: base_url.to_string(),
+            answered_threshold: 0.95,
+            batch_size,
+            timeout: Duration::from_secs(5),
         }
     }
-    updates
+
+    fn never_cancelled() -> CancelToken {
+        CancelToken::new(Arc::default())
+    }
+
+    fn agenda() -> Vec<AgendaItem> {
+        let mut hiring = item();
+        hiring.id = "2".to_string();
+        hiring.text = "Decide on hiring".to_string();
+        vec![item(), hiring]
+    }
+
+    // An Ollama /api/generate reply carrying `response`
+    fn generated(response: serde_json::Value) -> (u16, String) {
+        let response = match response {
+            serde_json::Value::String(text) => text,
+            value => value.to_string(),
+        };
+        (
+            200,
+            json!({ "model": "llama3.1:8b", "response": response, "done": true }).to_string(),
+        )
+    }
+
+    fn request_body(request: &str) -> serde_json::Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_sends_schema_and_options() {
+        let (base, requests) = crate::mock_server::mock_server(vec![generated(json!({
+            "match": true, "score": 1.3, "new_evidence": "Budget capped at 40k."
+        }))]);
+        let mut items = vec![item()];
+
+        let report = score_agenda_items(
+            "The budget is 40k.",
+            &mut items,
+            &options(&base, 8),
+            &never_cancelled(),
+        );
+        assert_eq!(report.updated, vec!["1"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(items[0].score, 1.0);
+        assert_eq!(items[0].status, Answered);
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /api/generate "));
+        let body = request_body(&request);
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["format"], score_schema());
+        assert_eq!(body["options"]["temperature"], 0.0);
+        assert_eq!(body["options"]["num_ctx"], NUM_CTX);
+    }
+
+    #[test]
+    fn test_scores_a_batch_in_one_call() {
+        let (base, requests) = crate::mock_server::mock_server(vec![generated(json!([
+            { "id": "2", "match": true, "score": 0.5, "new_evidence": "Two roles open." },
+            { "id": "1", "match": false, "score": 0.0, "new_evidence": "" }
+        ]))]);
+        let mut items = agenda();
+
+        let report = score_agenda_items(
+            "We will hire two people.",
+            &mut items,
+            &options(&base, 8),
+            &never_cancelled(),
+        );
+        assert_eq!(report.updated, vec!["2"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(items[0].status, Pending);
+        assert_eq!(items[1].status, InProgress);
+        assert_eq!(items[1].evidence, vec!["Two roles open."]);
+        assert_eq!(
+            request_body(&requests.recv().unwrap())["format"],
+            batch_schema()
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_single_calls_and_reports_failures() {
+        let (base, requests) = crate::mock_server::mock_server(vec![
+            generated(json!("[{\"id\": \"1\", \"match\": tru")),
+            generated(json!({ "match": true, "score": 0.4, "new_evidence": "Budget discussed." })),
+            generated(json!("I am not sure.")),
+        ]);
+        let mut items = agenda();
+
+        let report = score_agenda_items(
+            "The budget is tight.",
+            &mut items,
+            &options(&base, 8),
+            &never_cancelled(),
+        );
+        assert_eq!(report.updated, vec!["1"]);
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[0].starts_with("Batch of 2 goals: unreadable reply"));
+        assert!(report.errors[1].starts_with("'Decide on hiring': unreadable reply"));
+        assert_eq!(items[0].status, InProgress);
+        assert_eq!(items[1].status, Pending);
+
+        assert_eq!(
+            request_body(&requests.recv().unwrap())["format"],
+            batch_schema()
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                request_body(&requests.recv().unwrap())["format"],
+                score_schema()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reports_server_errors() {
+        let (base, _requests) = crate::mock_server::mock_server(vec![(
+            404,
+            r#"{"error":"model 'llama3.1:8b' not found"}"#.to_string(),
+        )]);
+        let mut items = vec![item()];
+
+        let report = score_agenda_items(
+            "The budget is 40k.",
+            &mut items,
+            &options(&base, 1),
+            &never_cancelled(),
+        );
+        assert!(report.updated.is_empty());
+        assert_eq!(
+            report.errors,
+            vec!["'Agree on the Q3 budget': Ollama returned 404 Not Found"]
+        );
+        assert_eq!(items[0].status, Pending);
+    }
+
+    #[test]
+    fn test_failed_batch_request_ends_the_pass() {
+        let (base, requests) = crate::mock_server::mock_server(vec![(
+            404,
+            r#"{"error":"model 'llama3.1:8b' not found"}"#.to_string(),
+        )]);
+        let mut items = agenda();
+
+        let report = score_agenda_items(
+            "The budget is tight.",
+            &mut items,
+            &options(&base, 8),
+            &never_cancelled(),
+        );
+        assert_eq!(
+            report.errors,
+            vec!["Batch of 2 goals: Ollama returned 404 Not Found"]
+        );
+        // No goal is retried on its own against a server that refused the batch
+        assert_eq!(requests.iter().count(), 1);
+        assert_eq!(items[0].status, Pending);
+        assert_eq!(items[1].status, Pending);
+    }
+
+    #[test]
+    fn test_retries_server_errors() {
+        let (base, requests) = crate::mock_server::mock_server(vec![
+            (503, r#"{"error":"server busy"}"#.to_string()),
+            generated(json!({ "match": true, "score": 0.5, "new_evidence": "Budget discussed." })),
+        ]);
+        let mut items = vec![item()];
+
+        let report = score_agenda_items(
+            "The budget is tight.",
+            &mut items,
+            &options(&base, 1),
+            &never_cancelled(),
+        );
+        assert_eq!(report.updated, vec!["1"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(requests.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_gives_up_after_timeouts() {
+        // Accepts connections but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut options = options(&format!("http://{}", listener.local_addr().unwrap()), 1);
+        options.timeout = Duration::from_millis(100);
+        let mut items = vec![item()];
+
+        let report = score_agenda_items(
+            "The budget is tight.",
+            &mut items,
+            &options,
+            &never_cancelled(),
+        );
+        assert_eq!(
+            report.errors,
+            vec!["'Agree on the Q3 budget': Ollama timed out"]
+        );
+        assert_eq!(items[0].status, Pending);
+    }
+
+    #[test]
+    fn test_cancelled_pass_makes_no_requests() {
+        let (base, requests) = crate::mock_server::mock_server(vec![generated(json!({
+            "match": true, "score": 0.5, "new_evidence": ""
+        }))]);
+        let generation = Arc::new(AtomicU64::new(0));
+        let cancel = CancelToken::new(generation.clone());
+        generation.fetch_add(1, Ordering::SeqCst);
+        let mut items = agenda();
+
+        let report = score_agenda_items(
+            "The budget is tight.",
+            &mut items,
+            &options(&base, 8),
+            &cancel,
+        );
+        assert!(cancel.is_cancelled());
+        assert!(report.updated.is_empty() && report.errors.is_empty());
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_merge_scored_by_id() {
+        let mut live = agenda();
+        let mut launch = item();
+        launch.id = "3".to_string();
+        live.push(launch);
+
+        let mut scored = live.clone();
+        for item in scored.iter_mut() {
+            item.score = 0.5;
+            item.transition(InProgress, 2_000).unwrap();
+        }
+        let updated: Vec<String> = scored.iter().map(|item| item.id.clone()).collect();
+
+        // While scoring, "2" is skipped by hand and "3" is removed
+        live[1].transition(Skipped, 1_500).unwrap();
+        live.pop();
+
+        assert_eq!(merge_scored(&mut live, &scored, &updated), vec!["1"]);
+        assert_eq!(live[0].status, InProgress);
+        assert_eq!(live[0].score, 0.5);
+        assert_eq!(live[1].status, Skipped);
+        assert_eq!(live[1].score, 0.0);
+    }
 }
@@ -1,34 +1,627 @@
-use crate::agenda::{score_agenda_items, AgendaItem};
+use crate::agenda::{
+    merge_scored, score_agenda_items, AgendaItem, CancelToken, ScoringOptions, REQUEST_TIMEOUT,
+};
 use crate::config::Config;
-use crate::transcription::run_transcription;
+use crate::embedding_cache::EmbeddingCache;
+use crate::hallucination::HallucinationFilter;
+use crate::level_meter::LevelMeter;
+use crate::ring_buffer::RingBuffer;
+use crate::session::SessionTranscript;
+use crate::source::{AudioSource, CaptureHandle, CpalSource, FileSource};
+use crate::streaming::{lock_streamer, Coalescer, StreamSegment, StreamingTranscriber};
+use crate::transcription::{
+    load_backend, BackendConfig, DecodeOptions, LanguageMode, TimedSegment, Transcriber,
+    Transcript, TranscriptSegment,
+};
+use crate::vad::{VadTracker, VoiceActivity};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::collections::VecDeque;
+use cpal::Sample;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Emitter};
-use whisper_rs::{WhisperContext, WhisperContextParameters};
 
 const SAMPLE_RATE: u32 = 16000;
+// A stream that delivers no callbacks for this long is treated as dead
+const STARVATION_TIMEOUT_MS: u64 = 3000;
+// Delay between reconnection attempts while a device is missing
+const RECONNECT_RETRY_SECS: u64 = 2;
+// How often to check whether a preferred device has been plugged back in
+const HOTPLUG_CHECK_SECS: u64 = 5;
+
+/// Which side of the conversation a capture device carries.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioRole {
+    /// System output routed back in (e.g. BlackHole): the remote participants
+    Loopback,
+    /// Local microphone: the user
+    Mic,
+}
+
+impl AudioRole {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioRole::Loopback => "Remote",
+            AudioRole::Mic => "You",
+        }
+    }
+
+    // The loopback channel keeps the original event names the UI already listens to
+    fn volume_event(&self) -> &'static str {
+        match self {
+            AudioRole::Loopback => "volume-level",
+            AudioRole::Mic => "mic-volume-level",
+        }
+    }
+
+    fn activity_event(&self) -> &'static str {
+        match self {
+            AudioRole::Loopback => "buffer-activity",
+            AudioRole::Mic => "mic-buffer-activity",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AudioDeviceInfo {
+    pub name: String,
+    pub role: Option<AudioRole>,
+}
+
+/// Liveness of a capture stream, updated from the realtime callback.
+#[derive(Default)]
+pub struct StreamHealth {
+    last_callback_ms: std::sync::atomic::AtomicU64,
+    failed: std::sync::atomic::AtomicBool,
+}
+
+impl StreamHealth {
+    fn reset(&self, now_ms: u64) {
+        self.last_callback_ms
+            .store(now_ms, std::sync::atomic::Ordering::Relaxed);
+        self.failed
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn touch(&self, now_ms: u64) {
+        self.last_callback_ms
+            .store(now_ms, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn mark_failed(&self) {
+        self.failed
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// True once the stream has reported an error or gone quiet for longer than `timeout_ms`.
+    pub fn needs_recovery(&self, now_ms: u64, timeout_ms: u64) -> bool {
+        let last = self
+            .last_callback_ms
+            .load(std::sync::atomic::Ordering::Relaxed);
+        self.failed.load(std::sync::atomic::Ordering::Relaxed)
+            || now_ms.saturating_sub(last) > timeout_ms
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Payload of the `audio-device-status` event.
+#[derive(Serialize, Clone, Debug)]
+pub struct DeviceStatus {
+    pub role: AudioRole,
+    /// "connected", "reconnecting" or "disconnected"
+    pub state: &'static str,
+    pub device: Option<String>,
+    pub message: Option<String>,
+}
+
+fn emit_device_status(
+    app_handle: &AppHandle,
+    role: AudioRole,
+    state: &'static str,
+    device: Option<String>,
+    message: Option<String>,
+) {
+    let _ = app_handle.emit(
+        "audio-device-status",
+        DeviceStatus {
+            role,
+            state,
+            device,
+            message,
+        },
+    );
+}
+
+/// Payload of the `whisper-model-status` event.
+#[derive(Serialize, Clone, Debug)]
+pub struct ModelStatus {
+    /// "loading", "loaded" or "failed"
+    pub state: &'static str,
+    pub path: String,
+    pub message: Option<String>,
+}
+
+fn emit_model_status(
+    app_handle: &AppHandle,
+    state: &'static str,
+    path: &str,
+    message: Option<String>,
+) {
+    let _ = app_handle.emit(
+        "whisper-model-status",
+        ModelStatus {
+            state,
+            path: path.to_string(),
+            message,
+        },
+    );
+}
+
+/// A channel's rolling audio and the level meter summarising it.
+///
+/// Resizing builds a replacement from the current audio and retires this one;
+/// the capture writer then moves over on its next block, carrying across
+/// whatever it wrote in between.
+pub struct CaptureBuffer {
+    pub ring: RingBuffer,
+    pub meter: LevelMeter,
+    retired: std::sync::atomic::AtomicBool,
+}
+
+impl CaptureBuffer {
+    fn new(max_samples: usize) -> Self {
+        CaptureBuffer {
+            ring: RingBuffer::new(max_samples),
+            meter: LevelMeter::new(max_samples),
+            retired: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    fn resized(&self, max_samples: usize) -> Self {
+        let ring = self.ring.resized(max_samples);
+        let meter = LevelMeter::with_history(max_samples, &ring.snapshot());
+        CaptureBuffer {
+            ring,
+            meter,
+            retired: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    // Copies whatever was written here after `next` was built from it
+    fn hand_over(&self, next: &CaptureBuffer) {
+        let missed = self
+            .ring
+            .total_written()
+            .saturating_sub(next.ring.total_written());
+        if missed > 0 {
+            let tail = self.ring.snapshot_last(missed as usize);
+            next.ring.push_slice(&tail);
+            next.meter.push(&tail);
+        }
+    }
+
+    fn clear(&self) {
+        self.ring.clear();
+        self.meter.clear();
+    }
+}
+
+/// One capture device with its own rolling buffer and stream.
+#[derive(Clone)]
+pub struct CaptureChannel {
+    pub role: AudioRole,
+    pub buffer: Arc<Mutex<Arc<CaptureBuffer>>>,
+    pub vad: Arc<VoiceActivity>,
+    pub device_name: Arc<Mutex<Option<String>>>,
+    /// The device the user asked for; the supervisor keeps trying to get back to it
+    pub preferred_device: Arc<Mutex<Option<String>>>,
+    pub stream_guard: Arc<Mutex<Option<CaptureHandle>>>,
+    pub health: Arc<StreamHealth>,
+}
+
+impl CaptureChannel {
+    fn new(role: AudioRole, max_samples: usize, silence_threshold: f32) -> Self {
+        CaptureChannel {
+            role,
+            buffer: Arc::new(Mutex::new(Arc::new(CaptureBuffer::new(max_samples)))),
+            vad: Arc::new(VoiceActivity::new(silence_threshold)),
+            device_name: Arc::new(Mutex::new(None)),
+            preferred_device: Arc::new(Mutex::new(None)),
+            stream_guard: Arc::new(Mutex::new(None)),
+            health: Arc::new(StreamHealth::default()),
+        }
+    }
+
+    fn open(
+        &self,
+        source: &dyn AudioSource,
+        is_recording: &Arc<std::sync::atomic::AtomicBool>,
+        app_handle: AppHandle,
+    ) -> Result<(), anyhow::Error> {
+        let name = source.name();
+        println!("{:?} input device: {}", self.role, name);
+
+        // The ring buffer takes a single writer: stop the old source, which
+        // waits for it to finish, and start the new one under the same lock so
+        // no other caller can start a writer in between
+        let mut stream = self.stream_guard.lock().unwrap();
+        *stream = None;
+
+        self.health.reset(now_ms());
+        let handle = source.start(CaptureSink {
+            buffer: self.buffer.clone(),
+            vad: self.vad.clone(),
+            is_recording: is_recording.clone(),
+            app_handle,
+            volume_event: self.role.volume_event(),
+            health: self.health.clone(),
+        })?;
+
+        *stream = Some(handle);
+        *self.device_name.lock().unwrap() = Some(name);
+        Ok(())
+    }
+
+    /// Opens `name`, or the system default input when `name` is missing or
+    /// cannot be opened and `fallback_to_default` is set. Announces a successful
+    /// connection through `audio-device-status` and returns the opened device name.
+    fn connect(
+        &self,
+        name: Option<&str>,
+        fallback_to_default: bool,
+        is_recording: &Arc<std::sync::atomic::AtomicBool>,
+        app_handle: &AppHandle,
+    ) -> Result<String, String> {
+        let mut result = Err("No input device found".to_string());
+
+        if let Some(name) = name {
+            result = find_input_device(name).and_then(|device| {
+                self.open(&CpalSource::new(device), is_recording, app_handle.clone())
+                    .map_err(|e| e.to_string())
+            });
+        }
+
+        if result.is_err() && fallback_to_default {
+            if let Some(device) = cpal::default_host().default_input_device() {
+                result = self
+                    .open(&CpalSource::new(device), is_recording, app_handle.clone())
+                    .map_err(|e| e.to_string());
+            }
+        }
+
+        result?;
+        let opened = self.device_name.lock().unwrap().clone().unwrap_or_default();
+        emit_device_status(
+            app_handle,
+            self.role,
+            "connected",
+            Some(opened.clone()),
+            None,
+        );
+        Ok(opened)
+    }
+
+    fn close(&self) {
+        *self.stream_guard.lock().unwrap() = None;
+        *self.device_name.lock().unwrap() = None;
+        *self.preferred_device.lock().unwrap() = None;
+        self.clear();
+    }
+
+    fn clear(&self) {
+        self.buffer().clear();
+    }
+
+    /// Changes how much audio the channel keeps, holding on to the most recent.
+    fn resize(&self, max_samples: usize) {
+        let mut slot = self.buffer.lock().unwrap();
+        if slot.ring.capacity() == max_samples {
+            return;
+        }
+        let resized = Arc::new(slot.resized(max_samples));
+        let old = std::mem::replace(&mut *slot, resized);
+        old.retired
+            .store(true, std::sync::atomic::Ordering::Release);
+    }
+
+    pub fn buffer(&self) -> Arc<CaptureBuffer> {
+        self.buffer.lock().unwrap().clone()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.stream_guard.lock().unwrap().is_some()
+    }
+}
+
+/// Point-in-time copy of every active capture buffer.
+pub struct CaptureSnapshot {
+    pub loopback: Vec<f32>,
+    pub mic: Option<Vec<f32>>,
+}
+
+impl CaptureSnapshot {
+    /// Copies the audio captured since loopback position `pos`, returning the
+    /// position the copy actually starts at.
+    ///
+    /// Positions come from the loopback channel; the mic track is cut to the
+    /// same length, tail-aligned as everywhere else.
+    pub fn take_from(loopback: &CaptureChannel, mic: &CaptureChannel, pos: u64) -> (u64, Self) {
+        let (start, samples) = loopback.buffer().ring.snapshot_from(pos);
+        let snapshot = CaptureSnapshot {
+            mic: if mic.is_active() {
+                Some(mic.buffer().ring.snapshot_last(samples.len()))
+            } else {
+                None
+            },
+            loopback: samples,
+        };
+        (start, snapshot)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loopback.is_empty() && self.mic.iter().all(|m| m.is_empty())
+    }
+
+    /// Both tracks summed into one, aligned on their most recent sample.
+    pub fn mixed(&self) -> Vec<f32> {
+        match &self.mic {
+            Some(mic) => mix_tracks(&self.loopback, mic),
+            None => self.loopback.clone(),
+        }
+    }
+}
+
+/// Sums two 16 kHz tracks that both end "now", padding the shorter one at the front.
+pub fn mix_tracks(a: &[f32], b: &[f32]) -> Vec<f32> {
+    let len = a.len().max(b.len());
+    let offset_a = len - a.len();
+    let offset_b = len - b.len();
+    (0..len)
+        .map(|i| {
+            let sa = if i >= offset_a { a[i - offset_a] } else { 0.0 };
+            let sb = if i >= offset_b { b[i - offset_b] } else { 0.0 };
+            (sa + sb).clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+/// Transcribes a capture snapshot according to `mix_mode`.
+///
+/// "mixed" runs Whisper once over the summed tracks; when a microphone is
+/// active each segment is then attributed to whichever track carried its
+/// energy. "separate" runs it per track instead. Segment times are relative
+/// to the start of the mix either way, and carry the speaker's role whenever
+/// a microphone track exists.
+pub fn transcribe_capture(
+    backend: &dyn Transcriber,
+    snapshot: &CaptureSnapshot,
+    mix_mode: &str,
+    options: &DecodeOptions,
+) -> Result<Vec<(Option<AudioRole>, TimedSegment)>, String> {
+    let mic = match &snapshot.mic {
+        Some(mic) => mic,
+        None => {
+            let segments = backend.transcribe(&snapshot.loopback, options)?;
+            return Ok(segments.into_iter().map(|s| (None, s)).collect());
+        }
+    };
+
+    if mix_mode != "separate" {
+        let segments = backend.transcribe(&snapshot.mixed(), options)?;
+        return Ok(label_segments(segments, &snapshot.loopback, mic));
+    }
+
+    let mix_len = snapshot.loopback.len().max(mic.len());
+    let mut labeled = Vec::new();
+    for (role, samples) in [
+        (AudioRole::Loopback, &snapshot.loopback),
+        (AudioRole::Mic, mic),
+    ] {
+        // The shorter track starts later in the mix
+        let offset_secs = (mix_len - samples.len()) as f32 / SAMPLE_RATE as f32;
+        for mut segment in backend.transcribe(samples, options)? {
+            segment.start_secs += offset_secs;
+            segment.end_secs += offset_secs;
+            labeled.push((Some(role), segment));
+        }
+    }
+    labeled.sort_by(|a, b| a.1.start_secs.total_cmp(&b.1.start_secs));
+    Ok(labeled)
+}
+
+/// Labels segments of a mixed transcription by speaker.
+///
+/// Segment times are relative to the start of the mix, which is as long as the
+/// longer track; both tracks end at the same instant. Each segment goes to the
+/// role with more energy over its span. Blank segments are dropped.
+pub fn label_segments(
+    segments: Vec<TimedSegment>,
+    loopback: &[f32],
+    mic: &[f32],
+) -> Vec<(Option<AudioRole>, TimedSegment)> {
+    let mix_len = loopback.len().max(mic.len());
+    segments
+        .into_iter()
+        .filter(|segment| !segment.text.trim().is_empty())
+        .map(|segment| {
+            let remote_energy =
+                span_energy(loopback, mix_len, segment.start_secs, segment.end_secs);
+            let mic_energy = span_energy(mic, mix_len, segment.start_secs, segment.end_secs);
+            let role = if mic_energy > remote_energy {
+                AudioRole::Mic
+            } else {
+                AudioRole::Loopback
+            };
+            (Some(role), segment)
+        })
+        .collect()
+}
+
+/// The Whisper prompt for a meeting: the user's glossary first, then the
+/// agenda questions, whose wording is likely to come up in the conversation.
+pub fn whisper_prompt(glossary: &[String], agenda: &[AgendaItem]) -> String {
+    crate::glossary::build_prompt(
+        glossary
+            .iter()
+            .map(String::as_str)
+            .chain(agenda.iter().map(|item| item.text.as_str())),
+    )
+}
+
+/// Renders transcript lines, prefixing each with its speaker's label when known.
+///
+/// Consecutive text from the same speaker is merged into one line.
+pub fn format_transcript<'a>(
+    segments: impl IntoIterator<Item = (Option<AudioRole>, &'a str)>,
+) -> String {
+    let mut lines: Vec<(Option<AudioRole>, String)> = Vec::new();
+
+    for (role, text) in segments {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        match lines.last_mut() {
+            Some((last_role, line)) if *last_role == role => {
+                line.push(' ');
+                line.push_str(text);
+            }
+            _ => lines.push((role, text.to_string())),
+        }
+    }
+
+    lines
+        .iter()
+        .map(|(role, text)| match role {
+            Some(role) => format!("{}: {}", role.label(), text),
+            None => text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs one streaming pass: transcribes the audio captured since `streamer`
+/// last committed (plus a short overlap) and returns the updated live transcript.
+/// Lines committed by the pass are also appended to `session`.
+///
+/// The newest captured sample is taken to have arrived just now; segment
+/// wall-clock times are counted back from it.
+pub fn transcribe_stream(
+    backend: &dyn Transcriber,
+    loopback: &CaptureChannel,
+    mic: &CaptureChannel,
+    streamer: &mut StreamingTranscriber,
+    session: &Mutex<SessionTranscript>,
+    mix_mode: &str,
+    options: &DecodeOptions,
+) -> Result<Transcript, String> {
+    streamer.prune(loopback.buffer().ring.oldest_position());
+
+    let (window_start, snapshot) =
+        CaptureSnapshot::take_from(loopback, mic, streamer.resume_from());
+    let window_end = window_start + snapshot.loopback.len() as u64;
+    if snapshot.is_empty() {
+        return Ok(streamer.transcript());
+    }
+
+    let captured_at = now_ms();
+    let to_position = |secs: f32| window_start + (secs.max(0.0) * SAMPLE_RATE as f32) as u64;
+    let to_wall_clock = |pos: u64| {
+        let age_ms = window_end.saturating_sub(pos) * 1000 / SAMPLE_RATE as u64;
+        captured_at.saturating_sub(age_ms)
+    };
+    let segments = transcribe_capture(backend, &snapshot, mix_mode, options)?
+        .into_iter()
+        .map(|(role, segment)| {
+            let (start, end) = (
+                to_position(segment.start_secs),
+                to_position(segment.end_secs),
+            );
+            StreamSegment {
+                start,
+                end,
+                segment: TranscriptSegment {
+                    start_ms: to_wall_clock(start),
+                    end_ms: to_wall_clock(end),
+                    role,
+                    text: segment.text.trim().to_string(),
+                    original_text: segment.original_text,
+                    avg_token_prob: segment.avg_token_prob,
+                    no_speech_prob: segment.no_speech_prob,
+                    language: segment.language,
+                    language_prob: segment.language_prob,
+                },
+            }
+        })
+        .collect();
+    let committed = streamer.update(window_start, window_end, segments);
+    session
+        .lock()
+        .unwrap()
+        .extend(committed.into_iter().map(|s| s.segment));
+
+    Ok(streamer.transcript())
+}
+
+// Mean square of `track` over a span of the tail-aligned mix
+fn span_energy(track: &[f32], mix_len: usize, start_secs: f32, end_secs: f32) -> f32 {
+    let offset = mix_len - track.len();
+    let start = ((start_secs.max(0.0) * SAMPLE_RATE as f32) as usize).min(mix_len);
+    let end = ((end_secs.max(0.0) * SAMPLE_RATE as f32) as usize).clamp(start, mix_len);
+
+    let from = start.saturating_sub(offset);
+    let to = end.saturating_sub(offset);
+    if to <= from {
+        return 0.0;
+    }
+    let chunk = &track[from..to];
+    chunk.iter().map(|&s| s * s).sum::<f32>() / chunk.len() as f32
+}
 
-// Wrapper to make cpal::Stream Send/Sync for storage in Mutex
-pub struct SafeStream(#[allow(dead_code)] pub cpal::Stream);
-unsafe impl Send for SafeStream {}
-unsafe impl Sync for SafeStream {}
+fn find_input_device(name: &str) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    let devices = host.input_devices().map_err(|e| e.to_string())?;
+
+    devices
+        .into_iter()
+        .find(|d| d.name().unwrap_or("unknown".to_string()) == name)
+        .ok_or_else(|| "Device not found".to_string())
+}
 
 pub struct AudioState {
-    pub buffer: Arc<Mutex<VecDeque<f32>>>,
-    pub context: Arc<WhisperContext>,
-    pub last_transcript: Arc<Mutex<String>>,
+    pub loopback: CaptureChannel,
+    pub mic: CaptureChannel,
+    pub capture_mix_mode: Arc<Mutex<String>>,
+    // Swapped wholesale by `reload_whisper_model`; passes in flight keep the model they started with
+    pub backend: Arc<Mutex<Arc<dyn Transcriber>>>,
+    pub backend_config: Arc<Mutex<BackendConfig>>,
+    model_generation: Arc<std::sync::atomic::AtomicU64>,
+    pub last_transcript: Arc<Mutex<Transcript>>,
+    pub transcriber: Arc<Mutex<StreamingTranscriber>>,
+    /// Shared by the background worker and manual requests, see `Coalescer`
+    pub stream_pass: Arc<Coalescer<Transcript>>,
+    pub session_transcript: Arc<Mutex<SessionTranscript>>,
     pub last_updated: Arc<Mutex<std::time::Instant>>,
     pub is_recording: Arc<std::sync::atomic::AtomicBool>,
     pub silence_threshold: f32,
     pub transcription_mode: Arc<Mutex<String>>,
-    pub whisper_language: Arc<Mutex<String>>,
+    pub whisper_language: Arc<Mutex<LanguageMode>>,
+    pub glossary: Arc<Mutex<Vec<String>>>,
+    pub hallucination_filter: Arc<Mutex<HallucinationFilter>>,
     pub agenda: Arc<Mutex<Vec<AgendaItem>>>,
-    pub device_name: Arc<Mutex<String>>,
-    pub stream_guard: Arc<Mutex<Option<SafeStream>>>,
-    pub max_samples: usize,
+    // Bumped whenever the agenda is replaced; a scan in flight is then cancelled
+    agenda_generation: Arc<std::sync::atomic::AtomicU64>,
+    embedding_cache: Arc<Mutex<EmbeddingCache>>,
     pub transcription_interval_secs: Arc<std::sync::atomic::AtomicU64>,
     pub agenda_check_cooldown_secs: Arc<std::sync::atomic::AtomicU64>,
+    pub agenda_batch_size: Arc<std::sync::atomic::AtomicUsize>,
     pub cache_freshness_secs: Arc<std::sync::atomic::AtomicU64>,
     pub ollama_base_url: Arc<Mutex<String>>,
     pub whisper_threads: Arc<std::sync::atomic::AtomicUsize>,
@@ -37,66 +630,96 @@ pub struct AudioState {
 
 impl AudioState {
     pub fn new(config: &Config, app_handle: AppHandle) -> Result<Self, anyhow::Error> {
-        let host = cpal::default_host();
-
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
-
-        let device_name_str = device.name().unwrap_or("unknown".to_string());
-        println!("Input device: {}", device_name_str);
-
         let duration_secs = config.buffer_duration_secs;
         let max_samples = (SAMPLE_RATE as usize) * duration_secs;
 
-        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(max_samples)));
         let is_recording = Arc::new(std::sync::atomic::AtomicBool::new(true));
 
-        // Create initial stream
-        let stream = create_stream(
-            &device,
-            &buffer,
-            &is_recording,
-            app_handle.clone(),
-            max_samples,
-        )?;
-        let stream_guard = Arc::new(Mutex::new(Some(SafeStream(stream))));
-
-        // Load Whisper model
-        println!("Loading Whisper model from: {}", config.whisper_ggml_path);
-        let ctx = WhisperContext::new_with_params(
-            &config.whisper_ggml_path,
-            WhisperContextParameters::default(),
-        )
-        .map_err(|e| anyhow::anyhow!("Failed to load whisper model: {}", e))?;
-
-        let ctx = Arc::new(ctx);
-        let last_transcript = Arc::new(Mutex::new(String::new()));
+        // Create initial streams: the default device (or a replay file) carries the
+        // remote side, the microphone is optional and only opened when configured
+        let loopback =
+            CaptureChannel::new(AudioRole::Loopback, max_samples, config.silence_threshold);
+        match config
+            .audio_source_file
+            .as_deref()
+            .filter(|p| !p.is_empty())
+        {
+            Some(path) => {
+                let source = FileSource::new(path, config.replay_speed);
+                loopback.open(&source, &is_recording, app_handle.clone())?;
+            }
+            None => {
+                let device = cpal::default_host()
+                    .default_input_device()
+                    .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
+                let source = CpalSource::new(device);
+                loopback.open(&source, &is_recording, app_handle.clone())?;
+                *loopback.preferred_device.lock().unwrap() =
+                    loopback.device_name.lock().unwrap().clone();
+            }
+        }
+
+        // A microphone that is missing at launch is picked up by the supervisor once plugged in
+        let mic = CaptureChannel::new(AudioRole::Mic, max_samples, config.silence_threshold);
+        if let Some(mic_name) = config.mic_device.as_deref().filter(|n| !n.is_empty()) {
+            *mic.preferred_device.lock().unwrap() = Some(mic_name.to_string());
+            let opened = find_input_device(mic_name).and_then(|d| {
+                mic.open(&CpalSource::new(d), &is_recording, app_handle.clone())
+                    .map_err(|e| e.to_string())
+            });
+            if let Err(e) = opened {
+                eprintln!("Failed to open microphone '{}': {}", mic_name, e);
+            }
+        }
+
+        // Load Whisper model, or connect to the transcription server
+        let backend_config = BackendConfig::from_config(config);
+        println!("Loading transcription backend: {:?}", backend_config);
+        let backend = load_backend(&backend_config).map_err(|e| anyhow::anyhow!(e))?;
+
+        let last_transcript = Arc::new(Mutex::new(Transcript::default()));
         let last_updated = Arc::new(Mutex::new(std::time::Instant::now()));
         let agenda = Arc::new(Mutex::new(Vec::new()));
         let transcription_mode = Arc::new(Mutex::new(config.transcription_mode.clone()));
-        let whisper_language = Arc::new(Mutex::new(config.whisper_language.clone()));
-        let device_name = Arc::new(Mutex::new(device_name_str));
+        let whisper_language = Arc::new(Mutex::new(LanguageMode::from_config(
+            &config.whisper_language,
+            &config.whisper_language_allowlist,
+        )));
 
         let audio_state = AudioState {
-            buffer,
-            context: ctx,
+            loopback,
+            mic,
+            capture_mix_mode: Arc::new(Mutex::new(config.capture_mix_mode.clone())),
+            backend: Arc::new(Mutex::new(backend)),
+            backend_config: Arc::new(Mutex::new(backend_config)),
+            model_generation: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             last_transcript,
             last_updated,
+            transcriber: Arc::new(Mutex::new(StreamingTranscriber::default())),
+            stream_pass: Arc::new(Coalescer::default()),
+            session_transcript: Arc::new(Mutex::new(SessionTranscript::default())),
             is_recording,
             silence_threshold: config.silence_threshold,
             transcription_mode,
             whisper_language,
+            glossary: Arc::new(Mutex::new(config.whisper_glossary.clone())),
+            hallucination_filter: Arc::new(Mutex::new(HallucinationFilter::new(
+                &config.whisper_blocklist,
+            ))),
             agenda,
-            device_name,
-            stream_guard,
-            max_samples,
+            agenda_generation: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            embedding_cache: Arc::new(Mutex::new(EmbeddingCache::load(
+                &Config::get_app_data_dir().join("embeddings.json"),
+            ))),
             transcription_interval_secs: Arc::new(std::sync::atomic::AtomicU64::new(
                 config.transcription_interval_secs,
             )),
             agenda_check_cooldown_secs: Arc::new(std::sync::atomic::AtomicU64::new(
                 config.agenda_check_cooldown_secs,
             )),
+            agenda_batch_size: Arc::new(std::sync::atomic::AtomicUsize::new(
+                config.agenda_batch_size,
+            )),
             cache_freshness_secs: Arc::new(std::sync::atomic::AtomicU64::new(
                 config.cache_freshness_secs,
             )),
@@ -106,84 +729,52 @@ impl AudioState {
         };
 
         audio_state.spawn_worker(config, app_handle.clone());
-        audio_state.spawn_buffer_monitor(app_handle);
+        audio_state.spawn_buffer_monitor(app_handle.clone());
+        audio_state.spawn_device_supervisor(app_handle);
 
         Ok(audio_state)
     }
 
-    fn spawn_buffer_monitor(&self, app_handle: AppHandle) {
-        let buffer_bg = self.buffer.clone();
-        let is_recording_bg = self.is_recording.clone();
-        let max_samples = self.max_samples;
+    /// Decoding settings for the next pass, as currently configured.
+    pub fn decode_options(&self) -> DecodeOptions {
+        DecodeOptions {
+            threshold: self.silence_threshold,
+            mode: self.transcription_mode.lock().unwrap().clone(),
+            language: self.whisper_language.lock().unwrap().clone(),
+            threads: self
+                .whisper_threads
+                .load(std::sync::atomic::Ordering::Relaxed),
+            prompt: whisper_prompt(&self.glossary.lock().unwrap(), &self.agenda.lock().unwrap()),
+            filter: self.hallucination_filter.lock().unwrap().clone(),
+        }
+    }
 
-        std::thread::spawn(move || {
-            loop {
-                std::thread::sleep(std::time::Duration::from_millis(250));
+    pub fn channel(&self, role: AudioRole) -> &CaptureChannel {
+        match role {
+            AudioRole::Loopback => &self.loopback,
+            AudioRole::Mic => &self.mic,
+        }
+    }
 
-                if !is_recording_bg.load(std::sync::atomic::Ordering::Relaxed) {
-                    continue;
-                }
+    fn spawn_buffer_monitor(&self, app_handle: AppHandle) {
+        let channels = [self.loopback.clone(), self.mic.clone()];
+        let is_recording_bg = self.is_recording.clone();
 
-                let samples: Vec<f32> = {
-                    let guard = match buffer_bg.lock() {
-                        Ok(g) => g,
-                        Err(_) => continue,
-                    };
-                    guard.iter().cloned().collect()
-                };
+        std::thread::spawn(move || loop {
+            std::thread::sleep(std::time::Duration::from_millis(250));
 
-                // Fixed 100 buckets spread across the TOTAL possible buffer duration
-                let num_buckets = 100;
-                let bucket_size = max_samples / num_buckets;
+            if !is_recording_bg.load(std::sync::atomic::Ordering::Relaxed) {
+                continue;
+            }
 
-                if bucket_size == 0 {
+            for channel in &channels {
+                if !channel.is_active() {
                     continue;
                 }
 
-                let mut levels = Vec::with_capacity(num_buckets);
-                let current_len = samples.len();
-
-                for i in 0..num_buckets {
-                    let start_idx = i * bucket_size;
-                    let end_idx = (i + 1) * bucket_size;
-
-                    // The 'samples' we have always represent the TAIL of the 45s window
-                    // Calculate where this bucket falls relative to the current live tail
-                    let virtual_start = if max_samples > current_len {
-                        max_samples - current_len
-                    } else {
-                        0
-                    };
-
-                    let level = if end_idx <= virtual_start {
-                        // This bucket is in the "future" (unfilled part of the history)
-                        0.0
-                    } else {
-                        let actual_start = if start_idx < virtual_start {
-                            0
-                        } else {
-                            start_idx - virtual_start
-                        };
-                        let actual_end = end_idx - virtual_start;
-
-                        // Safety check for indices
-                        if actual_start < current_len && actual_end <= current_len {
-                            let chunk = &samples[actual_start..actual_end];
-                            if chunk.is_empty() {
-                                0.0
-                            } else {
-                                (chunk.iter().map(|&s| s * s).sum::<f32>() / chunk.len() as f32)
-                                    .sqrt()
-                            }
-                        } else {
-                            0.0
-                        }
-                    };
-
-                    levels.push(level);
+                if let Some(levels) = channel.buffer().meter.levels() {
+                    let _ = app_handle.emit(channel.role.activity_event(), levels);
                 }
-
-                let _ = app_handle.emit("buffer-activity", levels);
             }
         });
     }
@@ -198,63 +789,299 @@ impl AudioState {
         }
     }
 
+    /// The role a device is currently capturing for, if any.
+    pub fn role_of(&self, device_name: &str) -> Option<AudioRole> {
+        [&self.loopback, &self.mic]
+            .into_iter()
+            .find(|c| c.device_name.lock().unwrap().as_deref() == Some(device_name))
+            .map(|c| c.role)
+    }
+
     pub fn switch_device(
         &self,
         new_device_name: String,
+        role: AudioRole,
         app_handle: AppHandle,
-        config: &Config,
     ) -> Result<(), String> {
-        let host = cpal::default_host();
-        let devices = host.input_devices().map_err(|e| e.to_string())?;
+        let channel = self.channel(role);
 
-        let device = devices
-            .into_iter()
-            .find(|d| d.name().unwrap_or("unknown".to_string()) == new_device_name)
-            .ok_or_else(|| "Device not found".to_string())?;
+        // The microphone is optional; an empty name turns it off
+        if role == AudioRole::Mic && new_device_name.is_empty() {
+            channel.close();
+            return Ok(());
+        }
 
-        // Calculate max_samples from config
-        let duration_secs = config.buffer_duration_secs;
-        let max_samples = (SAMPLE_RATE as usize) * duration_secs;
+        channel.connect(
+            Some(&new_device_name),
+            false,
+            &self.is_recording,
+            &app_handle,
+        )?;
 
         // Clear buffer when switching devices? Maybe strictly not necessary but safer.
-        {
-            let mut buf_guard = self.buffer.lock().unwrap();
-            buf_guard.clear();
-        }
+        channel.clear();
+        *channel.preferred_device.lock().unwrap() = Some(new_device_name);
 
-        let new_stream = create_stream(
-            &device,
-            &self.buffer,
-            &self.is_recording,
-            app_handle,
-            max_samples,
-        )
-        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
 
-        // Swap the stream
-        {
-            let mut stream_guard = self.stream_guard.lock().unwrap();
-            *stream_guard = Some(SafeStream(new_stream));
-        }
+    /// Replaces a channel's device with a recorded file, for testing and replaying meetings.
+    pub fn use_file_source(
+        &self,
+        path: String,
+        speed: f32,
+        role: AudioRole,
+        app_handle: AppHandle,
+    ) -> Result<(), String> {
+        let channel = self.channel(role);
+
+        channel.clear();
+        channel
+            .open(
+                &FileSource::new(&path, speed),
+                &self.is_recording,
+                app_handle.clone(),
+            )
+            .map_err(|e| e.to_string())?;
+
+        // A replay is not a device; keep the supervisor from "recovering" it onto hardware
+        *channel.preferred_device.lock().unwrap() = None;
+        emit_device_status(
+            &app_handle,
+            role,
+            "connected",
+            channel.device_name.lock().unwrap().clone(),
+            None,
+        );
 
-        // Update device name
-        {
-            let mut name_guard = self.device_name.lock().unwrap();
-            *name_guard = new_device_name;
+        Ok(())
+    }
+
+    /// Watches every capture channel and reopens streams that error out or stop
+    /// delivering audio (e.g. an unplugged headset or a vanished BlackHole).
+    ///
+    /// Recovery first retries the preferred device by name; the loopback channel
+    /// then falls back to the system default input. While running on a fallback,
+    /// the preferred device is reclaimed as soon as it shows up again.
+    fn spawn_device_supervisor(&self, app_handle: AppHandle) {
+        let channels = [self.loopback.clone(), self.mic.clone()];
+        let is_recording = self.is_recording.clone();
+
+        std::thread::spawn(move || {
+            let start = std::time::Instant::now();
+            let mut next_attempt = [start, start];
+            let mut reconnecting = [false, false];
+            let mut last_hotplug_check = start;
+
+            loop {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                let now = std::time::Instant::now();
+                let check_hotplug = last_hotplug_check.elapsed().as_secs() >= HOTPLUG_CHECK_SECS;
+                if check_hotplug {
+                    last_hotplug_check = now;
+                }
+
+                for (i, channel) in channels.iter().enumerate() {
+                    let preferred = match channel.preferred_device.lock().unwrap().clone() {
+                        Some(name) => name,
+                        // Nothing requested for this role (e.g. mic disabled)
+                        None => continue,
+                    };
+
+                    let healthy = channel.is_active()
+                        && !channel
+                            .health
+                            .needs_recovery(now_ms(), STARVATION_TIMEOUT_MS);
+
+                    if healthy {
+                        reconnecting[i] = false;
+
+                        let current = channel.device_name.lock().unwrap().clone();
+                        if check_hotplug
+                            && current.as_deref() != Some(preferred.as_str())
+                            && AudioState::list_devices().contains(&preferred)
+                        {
+                            println!(
+                                "[Audio] Preferred {:?} device '{}' is back, switching",
+                                channel.role, preferred
+                            );
+                            let _ = channel.connect(
+                                Some(&preferred),
+                                false,
+                                &is_recording,
+                                &app_handle,
+                            );
+                        }
+                        continue;
+                    }
+
+                    if now < next_attempt[i] {
+                        continue;
+                    }
+
+                    if !reconnecting[i] {
+                        eprintln!(
+                            "[Audio] {:?} stream lost ({}), reconnecting...",
+                            channel.role, preferred
+                        );
+                        // Drop the dead stream so a failed retry doesn't look healthy
+                        *channel.stream_guard.lock().unwrap() = None;
+                        emit_device_status(
+                            &app_handle,
+                            channel.role,
+                            "reconnecting",
+                            Some(preferred.clone()),
+                            None,
+                        );
+                        reconnecting[i] = true;
+                    }
+
+                    let fallback = channel.role == AudioRole::Loopback;
+                    match channel.connect(Some(&preferred), fallback, &is_recording, &app_handle) {
+                        Ok(name) => {
+                            println!("[Audio] {:?} stream restored on '{}'", channel.role, name);
+                            reconnecting[i] = false;
+                        }
+                        Err(e) => {
+                            emit_device_status(
+                                &app_handle,
+                                channel.role,
+                                "disconnected",
+                                Some(preferred.clone()),
+                                Some(e),
+                            );
+                            next_attempt[i] =
+                                now + std::time::Duration::from_secs(RECONNECT_RETRY_SECS);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Applies a new `buffer_duration_secs` to every channel without interrupting capture.
+    pub fn resize_buffer(&self, duration_secs: usize) {
+        let max_samples = (SAMPLE_RATE as usize) * duration_secs;
+        self.loopback.resize(max_samples);
+        self.mic.resize(max_samples);
+    }
+
+    /// Replaces the agenda and cancels any scan still working on the old one.
+    pub fn replace_agenda(&self, items: Vec<AgendaItem>) {
+        let mut agenda = self.agenda.lock().unwrap();
+        *agenda = items;
+        self.agenda_generation
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Replaces the agenda right away, with the embeddings already cached, and
+    /// fetches the rest on a background thread, so a slow Ollama never holds up
+    /// an edit. Items whose text is unchanged by then get theirs once they arrive.
+    pub fn set_agenda(
+        &self,
+        mut items: Vec<AgendaItem>,
+        embedding_model: Option<String>,
+        base_url: String,
+    ) {
+        let Some(model) = embedding_model else {
+            self.replace_agenda(items);
+            return;
+        };
+        let missing = self
+            .embedding_cache
+            .lock()
+            .unwrap()
+            .fill(&model, &mut items);
+        self.replace_agenda(items);
+        if missing.is_empty() {
+            return;
         }
 
-        Ok(())
+        let cache = self.embedding_cache.clone();
+        let agenda = self.agenda.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = EmbeddingCache::fetch(&cache, &model, &missing, &base_url) {
+                eprintln!("Failed to generate embeddings for agenda items: {}", e);
+                return;
+            }
+            // Matched by text, so an item edited meanwhile does not get a stale vector
+            let mut cache = cache.lock().unwrap();
+            let mut agenda = agenda.lock().unwrap();
+            cache.fill(&model, &mut agenda);
+            cache.retain(agenda.iter().map(|item| item.text.as_str()));
+            drop(agenda);
+            if let Err(e) = cache.save() {
+                eprintln!("Failed to save agenda embeddings: {}", e);
+            }
+        });
+    }
+
+    /// The transcription backend currently in use.
+    pub fn backend(&self) -> Arc<dyn Transcriber> {
+        self.backend.lock().unwrap().clone()
+    }
+
+    /// Sets up `backend` on a background thread and swaps it in once ready; a
+    /// local model must first pass a test decode.
+    ///
+    /// Progress is reported through `whisper-model-status`. If loading fails the
+    /// current backend stays in use, and if another reload is requested meanwhile
+    /// the newer request wins.
+    pub fn reload_backend(&self, backend: BackendConfig, app_handle: AppHandle) {
+        // Bumped under the backend lock, which a finished load holds while it
+        // checks the generation and swaps, so a stale load can never land last
+        let generation = {
+            let _backend = self.backend.lock().unwrap();
+            self.model_generation
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+                + 1
+        };
+        let generation_bg = self.model_generation.clone();
+        let backend_bg = self.backend.clone();
+        let backend_config_bg = self.backend_config.clone();
+        let location = backend.location().to_string();
+
+        println!("Reloading transcription backend: {:?}", backend);
+        emit_model_status(&app_handle, "loading", &location, None);
+
+        std::thread::spawn(move || {
+            let loaded = load_backend(&backend);
+            let mut current = backend_bg.lock().unwrap();
+            if generation_bg.load(std::sync::atomic::Ordering::SeqCst) != generation {
+                return;
+            }
+
+            match loaded {
+                Ok(transcriber) => {
+                    *current = transcriber;
+                    *backend_config_bg.lock().unwrap() = backend;
+                    drop(current);
+                    println!("Transcription backend swapped to: {}", location);
+                    emit_model_status(&app_handle, "loaded", &location, None);
+                }
+                Err(e) => {
+                    drop(current);
+                    eprintln!("Failed to reload transcription backend: {}", e);
+                    emit_model_status(&app_handle, "failed", &location, Some(e));
+                }
+            }
+        });
     }
 
     pub fn clear_buffer(&self) {
-        let mut guard = self.buffer.lock().unwrap();
-        guard.clear();
+        self.loopback.clear();
+        self.mic.clear();
     }
 
     fn spawn_worker(&self, config: &Config, app_handle: AppHandle) {
-        let buffer_bg = self.buffer.clone();
-        let ctx_bg = self.context.clone();
+        let loopback_bg = self.loopback.clone();
+        let mic_bg = self.mic.clone();
+        let capture_mix_mode_bg = self.capture_mix_mode.clone();
+        let backend_bg = self.backend.clone();
         let transcript_bg = self.last_transcript.clone();
+        let transcriber_bg = self.transcriber.clone();
+        let stream_pass_bg = self.stream_pass.clone();
+        let session_bg = self.session_transcript.clone();
         let updated_bg = self.last_updated.clone();
         let detect_model = config.ollama_model.clone();
         let embedding_model = config.ollama_embedding_model.clone();
@@ -263,10 +1090,14 @@ impl AudioState {
         let silence_threshold = config.silence_threshold;
         let transcription_mode_bg = self.transcription_mode.clone();
         let whisper_language_bg = self.whisper_language.clone();
+        let glossary_bg = self.glossary.clone();
+        let hallucination_filter_bg = self.hallucination_filter.clone();
         let agenda_bg = self.agenda.clone();
+        let agenda_generation_bg = self.agenda_generation.clone();
         let similarity_threshold = config.agenda_similarity_threshold;
         let transcription_interval_secs_bg = self.transcription_interval_secs.clone();
         let agenda_check_cooldown_secs_bg = self.agenda_check_cooldown_secs.clone();
+        let agenda_batch_size_bg = self.agenda_batch_size.clone();
         let ollama_base_url_bg = self.ollama_base_url.clone();
         let whisper_threads_bg = self.whisper_threads.clone();
         let agenda_answered_threshold = self.agenda_answered_threshold;
@@ -274,6 +1105,8 @@ impl AudioState {
         std::thread::spawn(move || {
             let mut last_detected_text = String::new();
             let mut last_agenda_check = std::time::Instant::now();
+            let agenda_scanning = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let mut last_speech_frames = 0;
 
             loop {
                 let interval =
@@ -284,30 +1117,49 @@ impl AudioState {
                     continue;
                 }
 
-                let samples: Vec<f32> = {
-                    let guard = buffer_bg.lock().unwrap();
-                    guard.iter().cloned().collect()
-                };
-
-                if samples.is_empty() {
+                // Nothing new was said and nothing is left to confirm; the transcript still stands
+                let speech_frames = loopback_bg.vad.speech_frames() + mic_bg.vad.speech_frames();
+                let has_tentative = lock_streamer(&transcriber_bg).has_tentative();
+                if speech_frames == last_speech_frames && !has_tentative {
                     continue;
                 }
-
-                if let Ok(text) = run_transcription(
-                    &ctx_bg,
-                    &samples,
-                    silence_threshold,
-                    &transcription_mode_bg.lock().unwrap(),
-                    &whisper_language_bg.lock().unwrap(),
-                    whisper_threads_bg.load(std::sync::atomic::Ordering::Relaxed),
-                ) {
+                last_speech_frames = speech_frames;
+
+                let backend = backend_bg.lock().unwrap().clone();
+                let options = DecodeOptions {
+                    threshold: silence_threshold,
+                    mode: transcription_mode_bg.lock().unwrap().clone(),
+                    language: whisper_language_bg.lock().unwrap().clone(),
+                    threads: whisper_threads_bg.load(std::sync::atomic::Ordering::Relaxed),
+                    prompt: whisper_prompt(
+                        &glossary_bg.lock().unwrap(),
+                        &agenda_bg.lock().unwrap(),
+                    ),
+                    filter: hallucination_filter_bg.lock().unwrap().clone(),
+                };
+                // Copied out, so a pass that panics leaves the lock unpoisoned
+                let mix_mode = capture_mix_mode_bg.lock().unwrap().clone();
+                let result = stream_pass_bg.run_or_join(|| {
+                    transcribe_stream(
+                        backend.as_ref(),
+                        &loopback_bg,
+                        &mic_bg,
+                        &mut lock_streamer(&transcriber_bg),
+                        &session_bg,
+                        &mix_mode,
+                        &options,
+                    )
+                });
+
+                if let Ok(transcript) = result {
                     let mut t_guard = transcript_bg.lock().unwrap();
                     let mut u_guard = updated_bg.lock().unwrap();
-                    *t_guard = text.clone();
+                    *t_guard = transcript.clone();
                     *u_guard = std::time::Instant::now();
 
                     // Emit live transcript for UI
-                    let _ = app_handle.emit("live-transcript", text.clone());
+                    let _ = app_handle.emit("live-transcript", &transcript);
+                    let text = transcript.text;
 
                     // From here on, logic depends on Ollama and Agenda
                     // Cooldown: avoid spamming Ollama
@@ -329,11 +1181,7 @@ impl AudioState {
 
                     if let Some(model) = &detect_model {
                         if text.is_empty() {
-                            let rms: f32 = (samples.iter().map(|s| s * s).sum::<f32>()
-                                / samples.len() as f32)
-                                .sqrt();
-                            let status = format!("Listening... (silence, rms: {:.6})", rms);
-                            let _ = app_handle.emit("agenda-status", status);
+                            let _ = app_handle.emit("agenda-status", "Listening... (silence)");
                             continue;
                         }
 
@@ -348,48 +1196,39 @@ impl AudioState {
                         }
 
                         if text.len() >= min_chars {
+                            // One scan at a time; the next check covers what this one missed
+                            if agenda_scanning.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                                println!("[Agenda] Previous scan still running, skipping.");
+                                continue;
+                            }
                             println!(
                                 "[Agenda] Sufficient text ({} chars), checking agenda...",
                                 text.len()
                             );
                             let _ = app_handle.emit("agenda-status", "Scanning agenda...");
-                            let mut agenda_updates = Vec::new();
-                            {
-                                let mut agenda_items = agenda_bg.lock().unwrap();
-                                // We need to update items in place now, so we pass mutable reference
-                                if !agenda_items.is_empty() {
-                                    let updates = score_agenda_items(
-                                        model,
-                                        &text,
-                                        &mut agenda_items,
-                                        embedding_model.as_deref(),
-                                        similarity_threshold,
-                                        &ollama_base_url_bg.lock().unwrap(),
-                                        agenda_answered_threshold,
-                                    );
-                                    if !updates.is_empty() {
-                                        agenda_updates = updates;
-                                    }
-                                }
-                            }
-
-                            if !agenda_updates.is_empty() {
-                                let _ = app_handle
-                                    .emit("agenda-update", agenda_bg.lock().unwrap().clone());
-
-                                let status = format!(
-                                    "{} goals updated ({} chars)",
-                                    agenda_updates.len(),
-                                    text.len()
-                                );
-                                let _ = app_handle.emit("agenda-status", status);
-                                last_detected_text = text.clone();
-                            } else {
-                                let status =
-                                    format!("No updates ({} chars, ollama run)", text.len());
-                                let _ = app_handle.emit("agenda-status", status);
-                                last_detected_text = text;
-                            }
+                            let scoring = ScoringOptions {
+                                model: model.clone(),
+                                embedding_model: embedding_model.clone(),
+                                similarity_threshold,
+                                base_url: ollama_base_url_bg.lock().unwrap().clone(),
+                                answered_threshold: agenda_answered_threshold,
+                                batch_size: agenda_batch_size_bg
+                                    .load(std::sync::atomic::Ordering::Relaxed),
+                                timeout: REQUEST_TIMEOUT,
+                            };
+                            // Taken before the snapshot, so a change in between cancels the scan
+                            let cancel = CancelToken::new(agenda_generation_bg.clone());
+                            let snapshot = agenda_bg.lock().unwrap().clone();
+                            spawn_agenda_scan(
+                                text.clone(),
+                                snapshot,
+                                scoring,
+                                cancel,
+                                agenda_bg.clone(),
+                                agenda_scanning.clone(),
+                                app_handle.clone(),
+                            );
+                            last_detected_text = text;
                         } else {
                             let status = format!("Insufficient text ({} chars)", text.len());
                             let _ = app_handle.emit("agenda-status", status);
@@ -403,183 +1242,767 @@ impl AudioState {
 
 // Transcription and agenda logic completed.
 
-fn create_stream(
-    device: &cpal::Device,
-    buffer: &Arc<Mutex<VecDeque<f32>>>,
-    is_recording: &Arc<std::sync::atomic::AtomicBool>,
+/// Scores a snapshot of the agenda on a thread of its own, so neither the
+/// transcription worker nor edits to the agenda wait on Ollama. Results are
+/// merged back by item id, unless the agenda was replaced in the meantime.
+///
+/// A replaced agenda stops the scan at its next request to Ollama; one already
+/// in flight is waited out, for at most `ScoringOptions::timeout` per attempt.
+/// `scanning` is cleared when the thread ends, however it ends.
+fn spawn_agenda_scan(
+    text: String,
+    mut snapshot: Vec<AgendaItem>,
+    scoring: ScoringOptions,
+    cancel: CancelToken,
+    agenda: Arc<Mutex<Vec<AgendaItem>>>,
+    scanning: Arc<std::sync::atomic::AtomicBool>,
     app_handle: AppHandle,
-    max_samples: usize,
+) {
+    let scanning = ScanGuard(scanning);
+    std::thread::spawn(move || {
+        let _scanning = scanning;
+        let report = score_agenda_items(&text, &mut snapshot, &scoring, &cancel);
+
+        // Checked under the lock, which `replace_agenda` holds while bumping the generation
+        let merged = {
+            let mut live = agenda.lock().unwrap();
+            if cancel.is_cancelled() {
+                None
+            } else {
+                let merged = merge_scored(&mut live, &snapshot, &report.updated);
+                if !merged.is_empty() {
+                    let _ = app_handle.emit("agenda-update", live.clone());
+                }
+                Some(merged)
+            }
+        };
+
+        let status = match merged {
+            None => {
+                println!("[Agenda] Agenda changed during the scan, results dropped.");
+                "Agenda changed, scan cancelled".to_string()
+            }
+            Some(merged) => {
+                if let Some(error) = report.errors.last() {
+                    format!(
+                        "{} goals updated, {} scoring errors. Last: {}",
+                        merged.len(),
+                        report.errors.len(),
+                        error
+                    )
+                } else if !merged.is_empty() {
+                    format!("{} goals updated ({} chars)", merged.len(), text.len())
+                } else {
+                    format!("No updates ({} chars, ollama run)", text.len())
+                }
+            }
+        };
+        let _ = app_handle.emit("agenda-status", status);
+    });
+}
+
+// Lets the worker start the next scan once this one is over, even if it panicked
+struct ScanGuard(Arc<std::sync::atomic::AtomicBool>);
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        self.0.store(false, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Opens a cpal input stream on `device` that feeds `sink`.
+pub(crate) fn create_stream(
+    device: &cpal::Device,
+    sink: CaptureSink,
 ) -> Result<cpal::Stream, anyhow::Error> {
     let stream_config = device.default_input_config()?;
     let input_sample_rate = stream_config.sample_rate().0;
-    println!("Stream Sample Rate: {}", input_sample_rate);
+    let channels = stream_config.channels() as usize;
+    println!(
+        "Stream Sample Rate: {}, Channels: {}, Format: {:?}",
+        input_sample_rate,
+        channels,
+        stream_config.sample_format()
+    );
 
-    let buffer_clone = buffer.clone();
-    let is_recording_data = is_recording.clone();
-    let err_fn = move |err| {
-        eprintln!("an error occurred on stream: {}", err);
+    let config = stream_config.config();
+
+    let stream = match stream_config.sample_format() {
+        cpal::SampleFormat::I8 => build_input_stream::<i8>(device, &config, sink)?,
+        cpal::SampleFormat::I16 => build_input_stream::<i16>(device, &config, sink)?,
+        cpal::SampleFormat::I32 => build_input_stream::<i32>(device, &config, sink)?,
+        cpal::SampleFormat::I64 => build_input_stream::<i64>(device, &config, sink)?,
+        cpal::SampleFormat::U8 => build_input_stream::<u8>(device, &config, sink)?,
+        cpal::SampleFormat::U16 => build_input_stream::<u16>(device, &config, sink)?,
+        cpal::SampleFormat::U32 => build_input_stream::<u32>(device, &config, sink)?,
+        cpal::SampleFormat::U64 => build_input_stream::<u64>(device, &config, sink)?,
+        cpal::SampleFormat::F32 => build_input_stream::<f32>(device, &config, sink)?,
+        cpal::SampleFormat::F64 => build_input_stream::<f64>(device, &config, sink)?,
+        other => return Err(anyhow::anyhow!("Unsupported sample format: {:?}", other)),
     };
 
-    let last_volume_emit = Arc::new(Mutex::new(std::time::Instant::now()));
+    stream.play()?;
+    Ok(stream)
+}
 
-    let stream = match stream_config.sample_format() {
-        cpal::SampleFormat::F32 => {
-            let last_emit = last_volume_emit.clone();
-            let app = app_handle.clone();
-            device.build_input_stream(
-                &stream_config.into(),
-                move |data: &[f32], _: &_| {
-                    if is_recording_data.load(std::sync::atomic::Ordering::Relaxed) {
-                        write_input_data(data, &buffer_clone, input_sample_rate, max_samples);
-
-                        if let Ok(mut last_emit_guard) = last_emit.try_lock() {
-                            if last_emit_guard.elapsed().as_millis() >= 100 {
-                                let rms = if data.is_empty() {
-                                    0.0
-                                } else {
-                                    (data.iter().map(|&s| s * s).sum::<f32>() / data.len() as f32)
-                                        .sqrt()
-                                };
-                                let _ = app.emit("volume-level", rms);
-                                *last_emit_guard = std::time::Instant::now();
-                            }
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            )?
-        }
-        cpal::SampleFormat::I16 => {
-            let buffer_clone_i16 = buffer_clone.clone();
-            let last_emit = last_volume_emit.clone();
-            let app = app_handle.clone();
-            device.build_input_stream(
-                &stream_config.into(),
-                move |data: &[i16], _: &_| {
-                    if is_recording_data.load(std::sync::atomic::Ordering::Relaxed) {
-                        write_input_data_i16(
-                            data,
-                            &buffer_clone_i16,
-                            input_sample_rate,
-                            max_samples,
-                        );
+/// Everything a capture source writes to, independent of where the audio comes from.
+pub struct CaptureSink {
+    buffer: Arc<Mutex<Arc<CaptureBuffer>>>,
+    vad: Arc<VoiceActivity>,
+    is_recording: Arc<std::sync::atomic::AtomicBool>,
+    app_handle: AppHandle,
+    volume_event: &'static str,
+    health: Arc<StreamHealth>,
+}
 
-                        if let Ok(mut last_emit_guard) = last_emit.try_lock() {
-                            if last_emit_guard.elapsed().as_millis() >= 100 {
-                                let rms = if data.is_empty() {
-                                    0.0
-                                } else {
-                                    (data
-                                        .iter()
-                                        .map(|&s| {
-                                            let f = s as f32 / i16::MAX as f32;
-                                            f * f
-                                        })
-                                        .sum::<f32>()
-                                        / data.len() as f32)
-                                        .sqrt()
-                                };
-                                let _ = app.emit("volume-level", rms);
-                                *last_emit_guard = std::time::Instant::now();
-                            }
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            )?
+impl CaptureSink {
+    /// Prepares a writer for interleaved input at `input_rate` with `channels` channels.
+    pub fn writer(self, input_rate: u32, channels: usize) -> CaptureWriter {
+        let buffer = self.buffer.lock().unwrap().clone();
+        CaptureWriter {
+            buffer,
+            converter: InputConverter::new(input_rate, channels),
+            converted: Vec::new(),
+            vad: VadTracker::default(),
+            last_volume_emit: std::time::Instant::now(),
+            sink: self,
+        }
+    }
+
+    pub fn health(&self) -> Arc<StreamHealth> {
+        self.health.clone()
+    }
+}
+
+/// Feeds normalised interleaved blocks into a channel's rolling buffer.
+pub struct CaptureWriter {
+    sink: CaptureSink,
+    buffer: Arc<CaptureBuffer>,
+    converter: InputConverter,
+    converted: Vec<f32>,
+    vad: VadTracker,
+    last_volume_emit: std::time::Instant,
+}
+
+impl CaptureWriter {
+    /// Downmixes/resamples into the rolling buffer, level meter and VAD, emitting `volume-level`.
+    pub fn write(&mut self, input: &[f32]) {
+        self.keep_alive();
+
+        if !self
+            .sink
+            .is_recording
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return;
+        }
+
+        if self
+            .buffer
+            .retired
+            .load(std::sync::atomic::Ordering::Acquire)
+        {
+            self.follow_resize();
+        }
+
+        self.converted.clear();
+        self.converter.process(input, &mut self.converted);
+        self.buffer.ring.push_slice(&self.converted);
+        self.buffer.meter.push(&self.converted);
+        self.vad.push(&self.converted, &self.sink.vad);
+
+        if self.last_volume_emit.elapsed().as_millis() >= 100 {
+            let rms = self.buffer.meter.take_volume();
+            let _ = self.sink.app_handle.emit(self.sink.volume_event, rms);
+            self.last_volume_emit = std::time::Instant::now();
         }
-        _ => return Err(anyhow::anyhow!("Unsupported sample format")),
+    }
+
+    /// Marks the source as alive without delivering audio.
+    pub fn keep_alive(&self) {
+        self.sink.health.touch(now_ms());
+    }
+
+    // Switches to the buffer that replaced ours, copying over what we wrote
+    // after it was built. Never waits: if the channel is busy, try next block.
+    fn follow_resize(&mut self) {
+        let next = match self.sink.buffer.try_lock() {
+            Ok(slot) => slot.clone(),
+            Err(_) => return,
+        };
+        self.buffer.hand_over(&next);
+        self.buffer = next;
+    }
+}
+
+/// Builds an input stream for any cpal sample type.
+///
+/// Every format goes through the same path: normalise to f32, then hand the
+/// block to the channel's `CaptureWriter`.
+fn build_input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    sink: CaptureSink,
+) -> Result<cpal::Stream, anyhow::Error>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let health = sink.health();
+    let err_fn = move |err| {
+        eprintln!("an error occurred on stream: {}", err);
+        // The supervisor picks this up and reopens the device
+        health.mark_failed();
     };
 
-    stream.play()?;
+    let mut writer = sink.writer(config.sample_rate.0, config.channels as usize);
+    let mut float_input = Vec::new();
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            float_input.clear();
+            samples_to_f32(data, &mut float_input);
+            writer.write(&float_input);
+        },
+        err_fn,
+        None,
+    )?;
+
     Ok(stream)
 }
 
-fn write_input_data(
-    input: &[f32],
-    buffer: &Arc<Mutex<VecDeque<f32>>>,
-    input_rate: u32,
-    max_samples: usize,
-) {
-    let mut guard = buffer.lock().unwrap();
-    let ratio = input_rate as f32 / SAMPLE_RATE as f32;
-    let mut index = 0.0;
+/// Normalises raw device samples of any cpal format to f32 in [-1.0, 1.0].
+fn samples_to_f32<T>(input: &[T], output: &mut Vec<f32>)
+where
+    T: cpal::Sample,
+    f32: cpal::FromSample<T>,
+{
+    output.extend(input.iter().map(|&s| f32::from_sample(s)));
+}
+
+// Zero crossings of the sinc kernel kept on each side of the centre tap.
+const RESAMPLER_ZERO_CROSSINGS: usize = 16;
+// Number of fractional phases precomputed in the polyphase table.
+const RESAMPLER_PHASES: usize = 256;
+// Cutoff as a fraction of the lower Nyquist frequency, leaving room for the transition band.
+const RESAMPLER_ROLLOFF: f64 = 0.9;
+
+/// Converts interleaved device frames into mono 16 kHz samples.
+///
+/// Channels are averaged down to mono, then a windowed-sinc polyphase filter
+/// band-limits and resamples the signal. Pending input and the fractional read
+/// position are carried across calls, so consecutive callbacks produce the same
+/// output as one contiguous block would.
+pub struct InputConverter {
+    channels: usize,
+    passthrough: bool,
+    // Input samples advanced per output sample
+    step: f64,
+    half_taps: usize,
+    // (RESAMPLER_PHASES + 1) rows of 2 * half_taps coefficients
+    table: Vec<f32>,
+    history: Vec<f32>,
+    // Position of the next output sample, in input samples relative to history[0]
+    pos: f64,
+}
+
+impl InputConverter {
+    pub fn new(input_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let input_rate = input_rate.max(1);
+        let passthrough = input_rate == SAMPLE_RATE;
+        let step = input_rate as f64 / SAMPLE_RATE as f64;
+
+        // Cutoff relative to the input Nyquist frequency
+        let cutoff = (SAMPLE_RATE as f64 / input_rate as f64).min(1.0) * RESAMPLER_ROLLOFF;
+        let half_taps = if passthrough {
+            0
+        } else {
+            (RESAMPLER_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize
+        };
+        let taps = 2 * half_taps;
+
+        let mut table = Vec::with_capacity((RESAMPLER_PHASES + 1) * taps);
+        for phase in 0..=RESAMPLER_PHASES {
+            let frac = phase as f64 / RESAMPLER_PHASES as f64;
+            let row: Vec<f64> = (0..taps)
+                .map(|j| {
+                    // Distance from the output instant to input tap j
+                    let x = (j as f64 - half_taps as f64 + 1.0) - frac;
+                    windowed_sinc(x, cutoff, half_taps as f64)
+                })
+                .collect();
+            // Normalise every phase to unity DC gain
+            let sum: f64 = row.iter().sum();
+            table.extend(row.iter().map(|&c| (c / sum) as f32));
+        }
+
+        // Pre-roll so the first output lines up with the first input sample
+        let history = vec![0.0; half_taps.saturating_sub(1)];
+        let pos = half_taps.saturating_sub(1) as f64;
+
+        InputConverter {
+            channels,
+            passthrough,
+            step,
+            half_taps,
+            table,
+            history,
+            pos,
+        }
+    }
+
+    /// Downmixes and resamples one interleaved block, appending 16 kHz mono samples to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let mono = input
+            .chunks_exact(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / self.channels as f32);
+
+        if self.passthrough {
+            output.extend(mono);
+            return;
+        }
+
+        self.history.extend(mono);
 
-    while (index as usize) < input.len() {
-        let val = input[index as usize];
-        guard.push_back(val);
-        if guard.len() > max_samples {
-            guard.pop_front();
+        let taps = 2 * self.half_taps;
+        loop {
+            let base = self.pos.floor();
+            let center = base as usize;
+            // Need inputs from center + 1 - half_taps up to center + half_taps
+            if center + self.half_taps >= self.history.len() {
+                break;
+            }
+
+            let scaled = (self.pos - base) * RESAMPLER_PHASES as f64;
+            let phase = scaled.floor() as usize;
+            let blend = (scaled - phase as f64) as f32;
+            let row_a = &self.table[phase * taps..(phase + 1) * taps];
+            let row_b = &self.table[(phase + 1) * taps..(phase + 2) * taps];
+            let window = &self.history[center + 1 - self.half_taps..=center + self.half_taps];
+
+            let mut acc_a = 0.0f32;
+            let mut acc_b = 0.0f32;
+            for ((&x, &a), &b) in window.iter().zip(row_a).zip(row_b) {
+                acc_a += x * a;
+                acc_b += x * b;
+            }
+            output.push(acc_a + (acc_b - acc_a) * blend);
+
+            self.pos += self.step;
+        }
+
+        // Drop input that no future output can reach
+        let consumed = (self.pos.floor() as usize + 1)
+            .saturating_sub(self.half_taps)
+            .min(self.history.len());
+        if consumed > 0 {
+            self.history.drain(..consumed);
+            self.pos -= consumed as f64;
         }
-        index += ratio;
     }
 }
 
-fn write_input_data_i16(
-    input: &[i16],
-    buffer: &Arc<Mutex<VecDeque<f32>>>,
-    input_rate: u32,
-    max_samples: usize,
-) {
-    let float_input: Vec<f32> = input.iter().map(|&x| x as f32 / i16::MAX as f32).collect();
-    write_input_data(&float_input, buffer, input_rate, max_samples);
+fn windowed_sinc(x: f64, cutoff: f64, half_width: f64) -> f64 {
+    if x.abs() >= half_width {
+        return 0.0;
+    }
+    let sinc = if x == 0.0 {
+        1.0
+    } else {
+        let arg = std::f64::consts::PI * cutoff * x;
+        arg.sin() / arg
+    };
+    // Blackman window over [-half_width, half_width]
+    let t = (x + half_width) / (2.0 * half_width);
+    let window = 0.42 - 0.5 * (2.0 * std::f64::consts::PI * t).cos()
+        + 0.08 * (4.0 * std::f64::consts::PI * t).cos();
+    cutoff * sinc * window
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::VecDeque;
-    use std::sync::{Arc, Mutex};
+
+    fn sine_sweep_stereo(rate: u32, secs: f32, f_start: f32, f_end: f32) -> Vec<f32> {
+        let frames = (rate as f32 * secs) as usize;
+        let mut out = Vec::with_capacity(frames * 2);
+        let mut phase = 0.0f32;
+        for i in 0..frames {
+            let t = i as f32 / frames as f32;
+            let freq = f_start + (f_end - f_start) * t;
+            phase += 2.0 * std::f32::consts::PI * freq / rate as f32;
+            let s = 0.5 * phase.sin();
+            out.push(s);
+            out.push(s);
+        }
+        out
+    }
+
+    fn convert_in_chunks(converter: &mut InputConverter, input: &[f32], chunk: usize) -> Vec<f32> {
+        let mut out = Vec::new();
+        for block in input.chunks(chunk) {
+            converter.process(block, &mut out);
+        }
+        out
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    // Power of a single frequency bin (Goertzel)
+    fn tone_power(samples: &[f32], freq: f32, rate: u32) -> f32 {
+        let coeff = 2.0 * (2.0 * std::f32::consts::PI * freq / rate as f32).cos();
+        let (mut s1, mut s2) = (0.0f32, 0.0f32);
+        for &x in samples {
+            let s0 = x + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        (s1 * s1 + s2 * s2 - coeff * s1 * s2) / samples.len() as f32
+    }
+
+    fn assert_normalized(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "got {}, expected {}", a, e);
+        }
+    }
 
     #[test]
-    fn test_write_input_data_push() {
-        let buffer = Arc::new(Mutex::new(VecDeque::new()));
-        let input = vec![1.0, 2.0, 3.0];
-        let input_rate = 16000;
-        let max_samples = 10;
+    fn test_samples_to_f32_signed_formats() {
+        let mut out = Vec::new();
+        samples_to_f32(&[i8::MIN, 0i8, 64, i8::MAX], &mut out);
+        // 8-bit formats top out one step below full scale
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 127.0 / 128.0]);
+
+        out.clear();
+        samples_to_f32(&[i16::MIN, 0i16, 16384, i16::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+
+        out.clear();
+        samples_to_f32(&[i32::MIN, 0i32, 1 << 30, i32::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+
+        out.clear();
+        samples_to_f32(&[i64::MIN, 0i64, 1 << 62, i64::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+    }
 
-        write_input_data(&input, &buffer, input_rate, max_samples);
+    #[test]
+    fn test_samples_to_f32_unsigned_formats() {
+        // Unsigned formats are centred on the midpoint
+        let mut out = Vec::new();
+        samples_to_f32(&[0u8, 128, 192, u8::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 127.0 / 128.0]);
+
+        out.clear();
+        samples_to_f32(&[0u16, 32768, 49152, u16::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+
+        out.clear();
+        samples_to_f32(&[0u32, 1 << 31, 3 << 30, u32::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+
+        out.clear();
+        samples_to_f32(&[0u64, 1 << 63, 3 << 62, u64::MAX], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.5, 1.0]);
+    }
 
-        let guard = buffer.lock().unwrap();
-        assert_eq!(guard.len(), 3);
-        assert_eq!(guard[0], 1.0);
-        assert_eq!(guard[2], 3.0);
+    #[test]
+    fn test_samples_to_f32_float_formats() {
+        let mut out = Vec::new();
+        samples_to_f32(&[-1.0f32, 0.0, 0.25, 1.0], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.25, 1.0]);
+
+        out.clear();
+        samples_to_f32(&[-1.0f64, 0.0, 0.25, 1.0], &mut out);
+        assert_normalized(&out, &[-1.0, 0.0, 0.25, 1.0]);
     }
 
     #[test]
-    fn test_write_input_data_max_samples() {
-        let buffer = Arc::new(Mutex::new(VecDeque::new()));
-        let input = vec![1.0, 2.0, 3.0, 4.0, 5.0];
-        let input_rate = 16000;
-        let max_samples = 3;
+    fn test_samples_to_f32_appends() {
+        let mut out = vec![0.5];
+        samples_to_f32(&[i16::MIN], &mut out);
+        assert_normalized(&out, &[0.5, -1.0]);
+    }
 
-        write_input_data(&input, &buffer, input_rate, max_samples);
+    #[test]
+    fn test_mix_tracks_aligns_tails() {
+        // The shorter track started later, so it lines up with the end of the longer one
+        let mixed = mix_tracks(&[0.1, 0.2, 0.3, 0.4], &[0.5, 0.5]);
+        assert_eq!(mixed.len(), 4);
+        assert!((mixed[0] - 0.1).abs() < 1e-6);
+        assert!((mixed[1] - 0.2).abs() < 1e-6);
+        assert!((mixed[2] - 0.8).abs() < 1e-6);
+        assert!((mixed[3] - 0.9).abs() < 1e-6);
+    }
 
-        let guard = buffer.lock().unwrap();
-        assert_eq!(guard.len(), 3);
-        // Should keep the last 3 samples
-        assert_eq!(guard[0], 3.0);
-        assert_eq!(guard[2], 5.0);
+    #[test]
+    fn test_mix_tracks_clamps() {
+        let mixed = mix_tracks(&[0.8, -0.8], &[0.8, -0.8]);
+        assert_eq!(mixed, vec![1.0, -1.0]);
     }
 
     #[test]
-    fn test_write_input_data_resampling() {
-        let buffer = Arc::new(Mutex::new(VecDeque::new()));
-        let input = vec![1.0, 2.0, 3.0, 4.0];
-        let input_rate = 32000; // 2x the standard rate
-        let max_samples = 10;
-
-        write_input_data(&input, &buffer, input_rate, max_samples);
-
-        let guard = buffer.lock().unwrap();
-        // At 32k -> 16k, we should skip every other sample
-        // index += 2.0
-        // index 0: 1.0
-        // index 2: 3.0
-        assert_eq!(guard.len(), 2);
-        assert_eq!(guard[0], 1.0);
-        assert_eq!(guard[1], 3.0);
+    fn test_snapshot_without_mic_is_loopback() {
+        let snapshot = CaptureSnapshot {
+            loopback: vec![0.1, 0.2],
+            mic: None,
+        };
+        assert_eq!(snapshot.mixed(), vec![0.1, 0.2]);
+        assert!(!snapshot.is_empty());
+
+        let empty = CaptureSnapshot {
+            loopback: vec![],
+            mic: Some(vec![]),
+        };
+        assert!(empty.is_empty());
+    }
+
+    fn attribute_segments(segments: &[TimedSegment], loopback: &[f32], mic: &[f32]) -> String {
+        let labeled = label_segments(segments.to_vec(), loopback, mic);
+        format_transcript(labeled.iter().map(|(role, s)| (*role, s.text.as_str())))
+    }
+
+    // Two tracks of `secs` seconds; each is loud only where its ranges say so
+    fn two_channel_fixture(
+        secs: f32,
+        remote_spans: &[(f32, f32)],
+        mic_spans: &[(f32, f32)],
+    ) -> (Vec<f32>, Vec<f32>) {
+        let len = (secs * SAMPLE_RATE as f32) as usize;
+        let render = |spans: &[(f32, f32)]| -> Vec<f32> {
+            (0..len)
+                .map(|i| {
+                    let t = i as f32 / SAMPLE_RATE as f32;
+                    let tone = (2.0 * std::f32::consts::PI * 220.0 * t).sin();
+                    if spans.iter().any(|&(a, b)| t >= a && t < b) {
+                        0.5 * tone
+                    } else {
+                        0.001 * tone
+                    }
+                })
+                .collect()
+        };
+        (render(remote_spans), render(mic_spans))
+    }
+
+    #[test]
+    fn test_attribute_segments_by_energy() {
+        let (loopback, mic) = two_channel_fixture(6.0, &[(0.0, 3.0)], &[(3.0, 6.0)]);
+        let segments = vec![
+            TimedSegment::new(0.2, 2.8, "What is the budget?"),
+            TimedSegment::new(3.1, 5.5, "Around ten thousand."),
+        ];
+
+        let text = attribute_segments(&segments, &loopback, &mic);
+        assert_eq!(
+            text,
+            "Remote: What is the budget?\nYou: Around ten thousand."
+        );
+    }
+
+    #[test]
+    fn test_attribute_segments_merges_same_speaker() {
+        let (loopback, mic) = two_channel_fixture(6.0, &[(4.0, 6.0)], &[(0.0, 4.0)]);
+        let segments = vec![
+            TimedSegment::new(0.0, 1.5, "First,"),
+            TimedSegment::new(1.5, 3.5, "second."),
+            TimedSegment::new(4.2, 5.8, "Got it."),
+        ];
+
+        let text = attribute_segments(&segments, &loopback, &mic);
+        assert_eq!(text, "You: First, second.\nRemote: Got it.");
+    }
+
+    #[test]
+    fn test_attribute_segments_tail_aligned_tracks() {
+        // The mic was opened 2 s after the loopback, so its track is shorter
+        let (loopback, _) = two_channel_fixture(6.0, &[(0.0, 2.0)], &[]);
+        let (_, mic_full) = two_channel_fixture(6.0, &[], &[(4.0, 6.0)]);
+        let mic = mic_full[2 * SAMPLE_RATE as usize..].to_vec();
+        let segments = vec![
+            TimedSegment::new(0.5, 1.5, "Hello?"),
+            TimedSegment::new(4.5, 5.5, "I'm here."),
+        ];
+
+        let text = attribute_segments(&segments, &loopback, &mic);
+        assert_eq!(text, "Remote: Hello?\nYou: I'm here.");
+    }
+
+    #[test]
+    fn test_attribute_segments_skips_blank_and_out_of_range() {
+        let (loopback, mic) = two_channel_fixture(2.0, &[], &[(0.0, 2.0)]);
+        let segments = vec![
+            TimedSegment::new(0.0, 1.0, "  "),
+            TimedSegment::new(0.5, 9.0, "Still me."),
+        ];
+
+        let text = attribute_segments(&segments, &loopback, &mic);
+        assert_eq!(text, "You: Still me.");
+    }
+
+    #[test]
+    fn test_capture_buffer_resize_keeps_recent_audio() {
+        let buffer = CaptureBuffer::new(1000);
+        let ramp: Vec<f32> = (0..900).map(|i| i as f32).collect();
+        buffer.ring.push_slice(&ramp);
+
+        let shrunk = buffer.resized(500);
+        assert_eq!(shrunk.ring.snapshot(), ramp[400..].to_vec());
+        assert!(shrunk.meter.levels().unwrap().iter().all(|&l| l > 0.0));
+
+        // The writer keeps going on the old buffer until it notices the swap
+        buffer.ring.push_slice(&[900.0, 901.0]);
+        buffer.hand_over(&shrunk);
+        let snapshot = shrunk.ring.snapshot();
+        assert_eq!(snapshot.len(), 500);
+        assert_eq!(snapshot[0], 402.0);
+        assert_eq!(snapshot[499], 901.0);
+
+        let grown = shrunk.resized(2000);
+        assert_eq!(grown.ring.snapshot(), snapshot);
+        let levels = grown.meter.levels().unwrap();
+        assert_eq!(levels[..75], [0.0; 75]);
+        assert!(levels[75..].iter().all(|&l| l > 0.0));
+    }
+
+    #[test]
+    fn test_stream_health_fresh_stream_is_healthy() {
+        let health = StreamHealth::default();
+        health.reset(10_000);
+        assert!(!health.needs_recovery(10_000, STARVATION_TIMEOUT_MS));
+        assert!(!health.needs_recovery(12_500, STARVATION_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn test_stream_health_detects_starvation() {
+        let health = StreamHealth::default();
+        health.reset(10_000);
+        health.touch(11_000);
+        assert!(!health.needs_recovery(13_900, STARVATION_TIMEOUT_MS));
+        assert!(health.needs_recovery(14_100, STARVATION_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn test_stream_health_error_needs_recovery_until_reset() {
+        let health = StreamHealth::default();
+        health.reset(10_000);
+        health.mark_failed();
+        // Callbacks may keep arriving, but an error still forces a reopen
+        health.touch(10_100);
+        assert!(health.needs_recovery(10_200, STARVATION_TIMEOUT_MS));
+
+        health.reset(10_300);
+        assert!(!health.needs_recovery(10_400, STARVATION_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn test_converter_passthrough_mono_16k() {
+        let mut converter = InputConverter::new(16000, 1);
+        let mut out = Vec::new();
+        converter.process(&[1.0, 2.0, 3.0], &mut out);
+        assert_eq!(out, vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_converter_downmix_stereo() {
+        let mut converter = InputConverter::new(16000, 2);
+        let mut out = Vec::new();
+        // L/R pairs
+        converter.process(&[1.0, 0.0, 0.5, -0.5, -1.0, -1.0], &mut out);
+        assert_eq!(out, vec![0.5, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn test_converter_output_length_44k_stereo() {
+        let input = sine_sweep_stereo(44100, 1.0, 100.0, 7000.0);
+        let mut converter = InputConverter::new(44100, 2);
+        let out = convert_in_chunks(&mut converter, &input, 512 * 2);
+        // Only the filter's look-ahead may still be pending
+        assert!(out.len() <= 16000);
+        assert!(out.len() >= 16000 - 32, "got {} samples", out.len());
+    }
+
+    #[test]
+    fn test_converter_output_length_48k_stereo() {
+        let input = sine_sweep_stereo(48000, 1.0, 100.0, 7000.0);
+        let mut converter = InputConverter::new(48000, 2);
+        let out = convert_in_chunks(&mut converter, &input, 480 * 2);
+        assert!(out.len() <= 16000);
+        assert!(out.len() >= 16000 - 32, "got {} samples", out.len());
+    }
+
+    #[test]
+    fn test_converter_passes_in_band_sweep() {
+        for rate in [44100, 48000] {
+            let input = sine_sweep_stereo(rate, 1.0, 100.0, 6500.0);
+            let mut converter = InputConverter::new(rate, 2);
+            let out = convert_in_chunks(&mut converter, &input, 1024);
+            // Skip the filter warm-up; a 0.5 amplitude sine has RMS ~0.354
+            let level = rms(&out[100..]);
+            assert!((level - 0.354).abs() < 0.02, "rate {}: rms {}", rate, level);
+        }
+    }
+
+    #[test]
+    fn test_converter_rejects_aliasing_sweep() {
+        for rate in [44100, 48000] {
+            // Everything here is above the 8 kHz output Nyquist and would fold back
+            let input = sine_sweep_stereo(rate, 1.0, 9000.0, 20000.0);
+            let mut converter = InputConverter::new(rate, 2);
+            let out = convert_in_chunks(&mut converter, &input, 1024);
+            let level = rms(&out[100..]);
+            assert!(level < 0.005, "rate {}: alias rms {}", rate, level);
+        }
+    }
+
+    #[test]
+    fn test_converter_tone_spectrum() {
+        // 1 kHz tone plus a 12 kHz tone that would alias to 4 kHz
+        for rate in [44100u32, 48000] {
+            let frames = rate as usize;
+            let mut input = Vec::with_capacity(frames * 2);
+            for i in 0..frames {
+                let t = i as f32 / rate as f32;
+                let s = 0.4 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+                    + 0.4 * (2.0 * std::f32::consts::PI * 12000.0 * t).sin();
+                input.push(s);
+                input.push(s);
+            }
+            let mut converter = InputConverter::new(rate, 2);
+            let out = convert_in_chunks(&mut converter, &input, 777);
+            let tail = &out[200..];
+            let wanted = tone_power(tail, 1000.0, SAMPLE_RATE);
+            let alias = tone_power(tail, 4000.0, SAMPLE_RATE);
+            assert!(
+                wanted > 1000.0 * alias,
+                "rate {}: {} vs {}",
+                rate,
+                wanted,
+                alias
+            );
+        }
+    }
+
+    #[test]
+    fn test_converter_block_edges_match_contiguous() {
+        let input = sine_sweep_stereo(44100, 0.5, 200.0, 4000.0);
+
+        let mut whole = InputConverter::new(44100, 2);
+        let mut expected = Vec::new();
+        whole.process(&input, &mut expected);
+
+        // Odd block sizes so frame boundaries never line up with output samples
+        let mut chunked = InputConverter::new(44100, 2);
+        let actual = convert_in_chunks(&mut chunked, &input, 2 * 37);
+
+        assert_eq!(expected.len(), actual.len());
+        for (a, b) in expected.iter().zip(&actual) {
+            assert!((a - b).abs() < 1e-6);
+        }
     }
 }
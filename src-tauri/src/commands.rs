@@ -1,16 +1,18 @@
-use crate::agenda::AgendaItem;
-use crate::audio::AudioState;
+use crate::agenda::{AgendaItem, AgendaStatus};
+use crate::audio::{transcribe_stream, AudioDeviceInfo, AudioRole, AudioState};
 use crate::config::Config;
-use crate::transcription::run_transcription;
+use crate::hallucination::HallucinationFilter;
+use crate::streaming::lock_streamer;
+use crate::transcription::{BackendConfig, LanguageMode, Transcript, TranscriptSegment};
 use crate::SessionState;
-use chrono::Local;
+use chrono::{Local, TimeZone};
 use std::fs::OpenOptions;
 use std::io::Write;
 use tauri::{AppHandle, Manager, State, Window};
 use tauri_plugin_global_shortcut::Shortcut;
 
 #[tauri::command]
-pub fn transcribe_latest(audio_state: State<AudioState>) -> Result<String, String> {
+pub fn transcribe_latest(audio_state: State<AudioState>) -> Result<Transcript, String> {
     // Check if background transcription is fresh
     {
         let freshness = audio_state
@@ -19,7 +21,7 @@ pub fn transcribe_latest(audio_state: State<AudioState>) -> Result<String, Strin
         let updated = audio_state.last_updated.lock().unwrap();
         if updated.elapsed().as_secs() < freshness {
             let cached = audio_state.last_transcript.lock().unwrap();
-            if !cached.is_empty() {
+            if !cached.segments.is_empty() {
                 println!(
                     "Returning pre-emptive cached transcript ({}s old)",
                     updated.elapsed().as_secs()
@@ -29,59 +31,90 @@ pub fn transcribe_latest(audio_state: State<AudioState>) -> Result<String, Strin
         }
     }
 
-    let samples: Vec<f32> = {
-        let guard = audio_state.buffer.lock().map_err(|e| e.to_string())?;
-        guard.iter().cloned().collect()
-    };
-
-    if samples.is_empty() {
-        return Ok("".to_string());
-    }
-
-    let text = run_transcription(
-        &audio_state.context,
-        &samples,
-        audio_state.silence_threshold,
-        &audio_state.transcription_mode.lock().unwrap(),
-        &audio_state.whisper_language.lock().unwrap(),
-        audio_state
-            .whisper_threads
-            .load(std::sync::atomic::Ordering::Relaxed),
-    )?;
+    // Catch the live transcript up to now, or take the result of the background
+    // pass if one is already running
+    let mix_mode = audio_state.capture_mix_mode.lock().unwrap().clone();
+    let transcript = audio_state.stream_pass.run_or_join(|| {
+        transcribe_stream(
+            audio_state.backend().as_ref(),
+            &audio_state.loopback,
+            &audio_state.mic,
+            &mut lock_streamer(&audio_state.transcriber),
+            &audio_state.session_transcript,
+            &mix_mode,
+            &audio_state.decode_options(),
+        )
+    })?;
 
     // Update cache
     let mut t_guard = audio_state.last_transcript.lock().unwrap();
     let mut u_guard = audio_state.last_updated.lock().unwrap();
-    *t_guard = text.clone();
+    *t_guard = transcript.clone();
     *u_guard = std::time::Instant::now();
 
-    Ok(text)
+    Ok(transcript)
+}
+
+#[tauri::command]
+pub fn get_session_transcript(audio_state: State<AudioState>) -> Transcript {
+    audio_state.session_transcript.lock().unwrap().full()
+}
+
+#[tauri::command]
+pub fn get_recent_transcript(audio_state: State<AudioState>, minutes: u64) -> Transcript {
+    let since = (Local::now().timestamp_millis() as u64).saturating_sub(minutes * 60_000);
+    audio_state.session_transcript.lock().unwrap().since(since)
 }
 
 #[tauri::command]
-pub fn get_audio_device(app: tauri::AppHandle) -> String {
+pub fn get_audio_device(app: tauri::AppHandle, role: Option<AudioRole>) -> String {
     match app.try_state::<AudioState>() {
         Some(state) => {
-            let guard = state.device_name.lock().unwrap();
-            guard.clone()
+            let channel = state.channel(role.unwrap_or(AudioRole::Loopback));
+            let guard = channel.device_name.lock().unwrap();
+            guard.clone().unwrap_or_default()
         }
         None => "No device detected".to_string(),
     }
 }
 
 #[tauri::command]
-pub fn list_audio_devices() -> Result<Vec<String>, String> {
-    Ok(AudioState::list_devices())
+pub fn list_audio_devices(app: tauri::AppHandle) -> Result<Vec<AudioDeviceInfo>, String> {
+    let state = app.try_state::<AudioState>();
+    Ok(AudioState::list_devices()
+        .into_iter()
+        .map(|name| {
+            let role = state.as_ref().and_then(|s| s.role_of(&name));
+            AudioDeviceInfo { name, role }
+        })
+        .collect())
 }
 
 #[tauri::command]
 pub fn set_audio_device(
     app: AppHandle,
     state: State<AudioState>,
-    config: State<Config>,
     name: String,
+    role: Option<AudioRole>,
 ) -> Result<(), String> {
-    state.switch_device(name, app, &config)
+    state.switch_device(name, role.unwrap_or(AudioRole::Loopback), app)
+}
+
+#[tauri::command]
+pub fn set_audio_source_file(
+    app: AppHandle,
+    state: State<AudioState>,
+    config: State<Config>,
+    path: String,
+    speed: Option<f32>,
+    role: Option<AudioRole>,
+) -> Result<(), String> {
+    state.use_file_source(
+        path,
+        speed.unwrap_or(config.replay_speed),
+        role.unwrap_or(AudioRole::Loopback),
+        app,
+    )
 }
 
 #[tauri::command]
@@ -98,32 +131,35 @@ pub fn transcribe_audio(_wav_path: String) -> Result<String, String> {
 pub fn update_agenda(
     audio_state: State<AudioState>,
     config: State<Config>,
-    mut items: Vec<AgendaItem>,
+    items: Vec<AgendaItem>,
 ) -> Result<(), String> {
-    // Generate embeddings for items that don't have them
-    if let Some(model) = &config.ollama_embedding_model {
-        for item in items.iter_mut() {
-            if item.embedding.is_none() {
-                if let Ok(emb) =
-                    crate::agenda::get_embedding(model, &item.text, &config.ollama_base_url)
-                {
-                    item.embedding = Some(emb);
-                } else {
-                    eprintln!(
-                        "Failed to generate embedding for agenda item: {}",
-                        item.text
-                    );
-                }
-            }
-        }
-    }
-
-    let mut guard = audio_state.agenda.lock().unwrap();
-    *guard = items;
-    println!("Updated agenda with {} items", guard.len());
+    println!("Updated agenda with {} items", items.len());
+    // Embeddings don't survive the trip through the UI; they come back from the
+    // cache, or from Ollama in the background
+    audio_state.set_agenda(
+        items,
+        config.ollama_embedding_model.clone(),
+        config.ollama_base_url.clone(),
+    );
     Ok(())
 }
 
+/// Skips, defers or reopens an agenda item by hand, and returns the updated agenda.
+#[tauri::command]
+pub fn set_agenda_status(
+    audio_state: State<AudioState>,
+    id: String,
+    status: AgendaStatus,
+) -> Result<Vec<AgendaItem>, String> {
+    let mut agenda = audio_state.agenda.lock().unwrap();
+    let item = agenda
+        .iter_mut()
+        .find(|item| item.id == id)
+        .ok_or_else(|| format!("No agenda item with id {}", id))?;
+    item.transition(status, crate::audio::now_ms())?;
+    Ok(agenda.clone())
+}
+
 #[tauri::command]
 pub fn hide_window(window: Window) -> Result<(), String> {
     window.hide().map_err(|e| e.to_string())
@@ -136,7 +172,7 @@ pub fn quit_app(app_handle: AppHandle) {
 
 #[tauri::command]
 pub fn log_session(
-    transcript: String,
+    transcript: Transcript,
     answer: String,
     state: State<SessionState>,
 ) -> Result<(), String> {
@@ -158,7 +194,9 @@ pub fn log_session(
     let timestamp = Local::now().format("%H:%M:%S").to_string();
     let log_entry = format!(
         "## [{}]\n\n**Transcript:**\n{}\n\n**Kuroko:**\n{}\n\n---\n\n",
-        timestamp, transcript, answer
+        timestamp,
+        format_transcript_markdown(&transcript),
+        answer
     );
 
     file.write_all(log_entry.as_bytes())
@@ -166,6 +204,48 @@ pub fn log_session(
     Ok(())
 }
 
+// One paragraph per speaker turn, stamped with the time it started and led by
+// a bold speaker label when we know who was talking. Translated turns keep
+// what was actually said in a quote underneath.
+fn format_transcript_markdown(transcript: &Transcript) -> String {
+    let mut turns: Vec<(&TranscriptSegment, Vec<&str>, Vec<&str>)> = Vec::new();
+    for segment in &transcript.segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let original = segment.original_text.as_deref().map(str::trim);
+        match turns.last_mut() {
+            Some((first, texts, originals)) if first.role == segment.role => {
+                texts.push(text);
+                originals.extend(original);
+            }
+            _ => turns.push((segment, vec![text], original.into_iter().collect())),
+        }
+    }
+
+    turns
+        .iter()
+        .map(|(first, texts, originals)| {
+            let time = Local
+                .timestamp_millis_opt(first.start_ms as i64)
+                .single()
+                .map(|t| t.format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            let text = texts.join(" ");
+            let mut paragraph = match first.role {
+                Some(role) => format!("**[{}] {}:** {}", time, role.label(), text),
+                None => format!("**[{}]** {}", time, text),
+            };
+            if !originals.is_empty() {
+                paragraph.push_str(&format!("\n> _{}_", originals.join(" ")));
+            }
+            paragraph
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 #[tauri::command]
 pub fn open_config_dir() -> Result<(), String> {
     let config_dir = Config::get_app_data_dir();
@@ -210,13 +290,31 @@ pub fn set_recording_state(state: State<AudioState>, active: bool) {
 }
 
 #[tauri::command]
-pub fn update_config(new_config: Config, audio_state: State<AudioState>) -> Result<(), String> {
+pub fn reload_whisper_model(app: AppHandle, audio_state: State<AudioState>, path: String) {
+    audio_state.reload_backend(BackendConfig::Local { model_path: path }, app);
+}
+
+#[tauri::command]
+pub fn update_config(
+    app: AppHandle,
+    new_config: Config,
+    audio_state: State<AudioState>,
+) -> Result<(), String> {
     // Update runtime state
     {
         let mut mode = audio_state.transcription_mode.lock().unwrap();
         *mode = new_config.transcription_mode.clone();
         let mut lang = audio_state.whisper_language.lock().unwrap();
-        *lang = new_config.whisper_language.clone();
+        *lang = LanguageMode::from_config(
+            &new_config.whisper_language,
+            &new_config.whisper_language_allowlist,
+        );
+        let mut glossary = audio_state.glossary.lock().unwrap();
+        *glossary = new_config.whisper_glossary.clone();
+        let mut filter = audio_state.hallucination_filter.lock().unwrap();
+        *filter = HallucinationFilter::new(&new_config.whisper_blocklist);
+        let mut mix_mode = audio_state.capture_mix_mode.lock().unwrap();
+        *mix_mode = new_config.capture_mix_mode.clone();
         audio_state.transcription_interval_secs.store(
             new_config.transcription_interval_secs,
             std::sync::atomic::Ordering::Relaxed,
@@ -225,10 +323,21 @@ pub fn update_config(new_config: Config, audio_state: State<AudioState>) -> Resu
             new_config.agenda_check_cooldown_secs,
             std::sync::atomic::Ordering::Relaxed,
         );
+        audio_state.agenda_batch_size.store(
+            new_config.agenda_batch_size,
+            std::sync::atomic::Ordering::Relaxed,
+        );
         audio_state.cache_freshness_secs.store(
             new_config.cache_freshness_secs,
             std::sync::atomic::Ordering::Relaxed,
         );
+        audio_state.resize_buffer(new_config.buffer_duration_secs);
+
+        let backend = BackendConfig::from_config(&new_config);
+        let backend_changed = *audio_state.backend_config.lock().unwrap() != backend;
+        if backend_changed {
+            audio_state.reload_backend(backend, app);
+        }
     }
 
     new_config.save().map_err(|e| e.to_string())?;
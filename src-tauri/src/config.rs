@@ -20,6 +20,14 @@ pub struct Config {
     pub silence_threshold: f32,
     pub transcription_mode: String,
     pub whisper_language: String,
+    #[serde(default)]
+    pub whisper_language_allowlist: Vec<String>,
+    /// Names and jargon Whisper should spell correctly, kept in glossary.txt
+    #[serde(default)]
+    pub whisper_glossary: Vec<String>,
+    /// Phantom lines dropped from Whisper output, kept in blocklist.txt
+    #[serde(default)]
+    pub whisper_blocklist: Vec<String>,
     pub agenda_similarity_threshold: f32,
     pub transcription_interval_secs: u64,
     pub agenda_check_cooldown_secs: u64,
@@ -28,6 +36,22 @@ pub struct Config {
     pub whisper_threads: usize,
     pub min_analysis_chars: usize,
     pub agenda_answered_threshold: f32,
+    /// Most agenda items scored in one Ollama prompt; 1 scores them one by one
+    pub agenda_batch_size: usize,
+    pub mic_device: Option<String>,
+    pub capture_mix_mode: String,
+    pub audio_source_file: Option<String>,
+    pub replay_speed: f32,
+    /// "local" (whisper-rs) or "http" (a transcription server)
+    #[serde(default)]
+    pub transcription_backend: String,
+    #[serde(default)]
+    pub transcription_url: String,
+    #[serde(default)]
+    pub transcription_api_key: Option<String>,
+    /// Model name sent to the server, e.g. "whisper-1" for OpenAI
+    #[serde(default)]
+    pub transcription_model: Option<String>,
     pub error: Option<String>,
 }
 
@@ -112,11 +136,11 @@ MIN_CONFIDENCE=0.5
 SILENCE_THRESHOLD=0.004
 
 # 8. Transcription Mode (Optional, Default: speed)
-# Options: speed, accuracy
+# Options: speed, accuracy, translate (transcribe into English)
 TRANSCRIPTION_MODE=speed
 
 # 9. Whisper Language (Optional, Default: en)
-# Options: en, zh, pl, fr
+# Options: en, zh, pl, fr, auto (detect the language of every chunk)
 WHISPER_LANGUAGE=en
 
 # 10. Agenda Similarity Threshold (Optional, Default: 0.35)
@@ -148,6 +172,41 @@ MIN_ANALYSIS_CHARS=25
 # 17. Agenda Answered Threshold (Optional, Default: 0.95)
 # Score at which an agenda item is considered "answered".
 AGENDA_ANSWERED_THRESHOLD=0.95
+
+# 18. Microphone Device (Optional)
+# Name of a second input device capturing your own voice, recorded alongside the default device.
+MIC_DEVICE=
+
+# 19. Capture Mix Mode (Optional, Default: mixed)
+# Options: mixed (transcribe both devices together), separate (transcribe each device on its own)
+CAPTURE_MIX_MODE=mixed
+
+# 20. Audio Source File (Optional)
+# Replay a recorded .wav (or raw 16 kHz mono 16-bit PCM) file instead of capturing from a device.
+AUDIO_SOURCE_FILE=
+
+# 21. Replay Speed (Optional, Default: 1.0)
+# 1.0 replays in real time, higher is faster, 0 pushes the whole file at once.
+REPLAY_SPEED=1.0
+
+# 22. Whisper Language Allow-list (Optional)
+# Comma-separated codes that auto-detection may choose from, e.g. en,pl. Empty allows any language.
+WHISPER_LANGUAGE_ALLOWLIST=
+
+# 23. Transcription Backend (Optional, Default: local)
+# Options: local (run the Whisper model above in-process), http (send audio to a transcription server)
+TRANSCRIPTION_BACKEND=local
+
+# 24. Transcription Server (Required for the http backend)
+# A whisper.cpp server, e.g. http://192.168.1.20:8080/inference,
+# or an OpenAI-compatible endpoint, e.g. https://api.openai.com/v1/audio/transcriptions
+TRANSCRIPTION_URL=
+TRANSCRIPTION_API_KEY=
+TRANSCRIPTION_MODEL=
+
+# 25. Agenda Batch Size (Optional, Default: 8)
+# Most agenda items scored in a single Ollama prompt. 1 scores every item with its own prompt.
+AGENDA_BATCH_SIZE=8
 "#;
             if let Err(e) = std::fs::write(&app_data_dir.join(".env"), default_env) {
                 println!("Warning: Failed to create .env template: {}", e);
@@ -161,14 +220,21 @@ AGENDA_ANSWERED_THRESHOLD=0.95
 
         let gemini_api_key = env::var("GEMINI_API_KEY").unwrap_or_default();
         let whisper_ggml_path = env::var("WHISPER_GGML_PATH").unwrap_or_default();
+        let transcription_backend =
+            env::var("TRANSCRIPTION_BACKEND").unwrap_or_else(|_| "local".to_string());
+        let transcription_url = env::var("TRANSCRIPTION_URL").unwrap_or_default();
+        let remote = transcription_backend.eq_ignore_ascii_case("http");
 
         let mut error = None;
-        if gemini_api_key.is_empty() || whisper_ggml_path.is_empty() {
+        if gemini_api_key.is_empty()
+            || (remote && transcription_url.is_empty())
+            || (!remote && whisper_ggml_path.is_empty())
+        {
             error = Some(format!(
                 "Setting Required. Open the folder and edit .env at: {:?}",
                 app_data_dir
             ));
-        } else if !Path::new(&whisper_ggml_path).exists() {
+        } else if !remote && !Path::new(&whisper_ggml_path).exists() {
             error = Some(format!(
                 "Whisper model not found at: {}. Please check your .env file in {:?}",
                 whisper_ggml_path, app_data_dir
@@ -212,6 +278,13 @@ AGENDA_ANSWERED_THRESHOLD=0.95
 
         let whisper_language = env::var("WHISPER_LANGUAGE").unwrap_or_else(|_| "en".to_string());
 
+        let whisper_language_allowlist = env::var("WHISPER_LANGUAGE_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty())
+            .collect();
+
         let agenda_similarity_threshold = env::var("AGENDA_SIMILARITY_THRESHOLD")
             .unwrap_or_else(|_| "0.35".to_string())
             .parse::<f32>()
@@ -250,6 +323,22 @@ AGENDA_ANSWERED_THRESHOLD=0.95
             .parse::<f32>()
             .unwrap_or(0.95);
 
+        let agenda_batch_size = env::var("AGENDA_BATCH_SIZE")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse::<usize>()
+            .unwrap_or(8);
+
+        let mic_device = env::var("MIC_DEVICE").ok().filter(|s| !s.is_empty());
+
+        let capture_mix_mode = env::var("CAPTURE_MIX_MODE").unwrap_or_else(|_| "mixed".to_string());
+
+        let audio_source_file = env::var("AUDIO_SOURCE_FILE").ok().filter(|s| !s.is_empty());
+
+        let replay_speed = env::var("REPLAY_SPEED")
+            .unwrap_or_else(|_| "1.0".to_string())
+            .parse::<f32>()
+            .unwrap_or(1.0);
+
         // Load prompt from file in App Data dir
         let mut prompt = String::new();
         let prompt_path = app_data_dir.join("prompt.txt");
@@ -265,6 +354,10 @@ AGENDA_ANSWERED_THRESHOLD=0.95
             let _ = std::fs::write(&prompt_path, &prompt);
         }
 
+        let whisper_glossary = crate::glossary::load(&app_data_dir.join("glossary.txt"));
+        let whisper_blocklist =
+            crate::hallucination::load_blocklist(&app_data_dir.join("blocklist.txt"));
+
         Ok(Config {
             gemini_api_key,
             gemini_model,
@@ -273,12 +366,17 @@ AGENDA_ANSWERED_THRESHOLD=0.95
             whisper_ggml_path,
             prompt,
             ollama_model,
-            ollama_embedding_model: env::var("OLLAMA_EMBEDDING_MODEL").ok(),
+            ollama_embedding_model: env::var("OLLAMA_EMBEDDING_MODEL")
+                .ok()
+                .filter(|s| !s.is_empty()),
             ollama_min_chars,
             min_confidence,
             silence_threshold,
             transcription_mode,
             whisper_language,
+            whisper_language_allowlist,
+            whisper_glossary,
+            whisper_blocklist,
             agenda_similarity_threshold,
             transcription_interval_secs,
             agenda_check_cooldown_secs,
@@ -287,6 +385,19 @@ AGENDA_ANSWERED_THRESHOLD=0.95
             whisper_threads,
             min_analysis_chars,
             agenda_answered_threshold,
+            agenda_batch_size,
+            mic_device,
+            capture_mix_mode,
+            audio_source_file,
+            replay_speed,
+            transcription_backend,
+            transcription_url,
+            transcription_api_key: env::var("TRANSCRIPTION_API_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            transcription_model: env::var("TRANSCRIPTION_MODEL")
+                .ok()
+                .filter(|s| !s.is_empty()),
             error,
         })
     }
@@ -299,6 +410,15 @@ AGENDA_ANSWERED_THRESHOLD=0.95
         // Write prompt.txt
         std::fs::write(&prompt_path, &self.prompt).map_err(|e| e.to_string())?;
 
+        // Write glossary.txt
+        crate::glossary::save(&app_data_dir.join("glossary.txt"), &self.whisper_glossary)
+            .map_err(|e| e.to_string())?;
+
+        // Write blocklist.txt
+        let mut blocklist = self.whisper_blocklist.join("\n");
+        blocklist.push('\n');
+        std::fs::write(app_data_dir.join("blocklist.txt"), blocklist).map_err(|e| e.to_string())?;
+
         // Write .env
         let env_content = format!(
             r#"# Kuroko Configuration
@@ -322,6 +442,16 @@ OLLAMA_BASE_URL={}
 WHISPER_THREADS={}
 MIN_ANALYSIS_CHARS={}
 AGENDA_ANSWERED_THRESHOLD={}
+MIC_DEVICE={}
+CAPTURE_MIX_MODE={}
+AUDIO_SOURCE_FILE={}
+REPLAY_SPEED={}
+WHISPER_LANGUAGE_ALLOWLIST={}
+TRANSCRIPTION_BACKEND={}
+TRANSCRIPTION_URL={}
+TRANSCRIPTION_API_KEY={}
+TRANSCRIPTION_MODEL={}
+AGENDA_BATCH_SIZE={}
 "#,
             self.gemini_api_key,
             self.whisper_ggml_path,
@@ -342,7 +472,17 @@ AGENDA_ANSWERED_THRESHOLD={}
             self.ollama_base_url,
             self.whisper_threads,
             self.min_analysis_chars,
-            self.agenda_answered_threshold
+            self.agenda_answered_threshold,
+            self.mic_device.as_deref().unwrap_or_default(),
+            self.capture_mix_mode,
+            self.audio_source_file.as_deref().unwrap_or_default(),
+            self.replay_speed,
+            self.whisper_language_allowlist.join(","),
+            self.transcription_backend,
+            self.transcription_url,
+            self.transcription_api_key.as_deref().unwrap_or_default(),
+            self.transcription_model.as_deref().unwrap_or_default(),
+            self.agenda_batch_size
         );
 
         std::fs::write(&env_path, env_content).map_err(|e| e.to_string())?;
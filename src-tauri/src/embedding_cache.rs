@@ -0,0 +1,263 @@
+use crate::agenda::AgendaItem;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Agenda item embeddings kept on disk, so an agenda coming back from the UI,
+/// or from the last launch, is not embedded all over again.
+///
+/// Vectors are keyed by a hash of the model and the text. The cache holds one
+/// model's vectors at a time; asking it for another model empties it. It is
+/// pruned to the current agenda, see `retain`.
+#[derive(Debug)]
+pub struct EmbeddingCache {
+    path: PathBuf,
+    file: CacheFile,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct CacheFile {
+    model: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl EmbeddingCache {
+    /// Reads the cache at `path`; a missing or unreadable file is an empty cache.
+    pub fn load(path: &Path) -> Self {
+        let file = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        EmbeddingCache {
+            path: path.to_path_buf(),
+            file,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string(&self.file).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, model: &str, text: &str) -> Option<&Vec<f32>> {
+        if self.file.model != model {
+            return None;
+        }
+        self.file.vectors.get(&key(model, text))
+    }
+
+    pub fn insert(&mut self, model: &str, text: &str, vector: Vec<f32>) {
+        if self.file.model != model {
+            self.file = CacheFile {
+                model: model.to_string(),
+                vectors: HashMap::new(),
+            };
+        }
+        self.file.vectors.insert(key(model, text), vector);
+    }
+
+    /// Gives items the embeddings the cache has, and returns the texts still
+    /// missing one, each once.
+    pub fn fill(&self, model: &str, items: &mut [AgendaItem]) -> Vec<String> {
+        let mut missing: Vec<String> = Vec::new();
+        for item in items.iter_mut() {
+            match self.get(model, &item.text) {
+                Some(vector) => item.embedding = Some(vector.clone()),
+                None => {
+                    if !missing.contains(&item.text) {
+                        missing.push(item.text.clone());
+                    }
+                }
+            }
+        }
+        missing
+    }
+
+    /// Embeds `texts` in one request to Ollama and adds them. The cache is only
+    /// locked once the reply is in, so a slow Ollama blocks no one else.
+    pub fn fetch(
+        cache: &Mutex<EmbeddingCache>,
+        model: &str,
+        texts: &[String],
+        base_url: &str,
+    ) -> Result<(), String> {
+        let vectors = crate::agenda::embed(model, texts, base_url)?;
+        let mut cache = cache.lock().unwrap();
+        for (text, vector) in texts.iter().zip(vectors) {
+            cache.insert(model, text, vector);
+        }
+        println!("[Agenda] Embedded {} new agenda items", texts.len());
+        Ok(())
+    }
+
+    /// Forgets every vector but those of `texts`. The agenda is sent over on
+    /// every pause in typing, so without this each half-typed item would stay.
+    pub fn retain<'a>(&mut self, texts: impl IntoIterator<Item = &'a str>) {
+        let keep: HashSet<String> = texts
+            .into_iter()
+            .map(|text| key(&self.file.model, text))
+            .collect();
+        self.file.vectors.retain(|key, _| keep.contains(key));
+    }
+}
+
+// FNV-1a, which unlike `DefaultHasher` is stable across Rust releases
+fn key(model: &str, text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in model.bytes().chain([0]).chain(text.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agenda::AgendaStatus;
+    use crate::mock_server::mock_server;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("kuroko_{}_{}.json", name, std::process::id()))
+    }
+
+    fn item(id: &str, text: &str) -> AgendaItem {
+        AgendaItem {
+            id: id.to_string(),
+            text: text.to_string(),
+            status: AgendaStatus::Pending,
+            answer: None,
+            score: 0.0,
+            evidence: Vec::new(),
+            history: Vec::new(),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn test_key_covers_model_and_text() {
+        assert_eq!(
+            key("nomic-embed-text", "Budget"),
+            key("nomic-embed-text", "Budget")
+        );
+        assert_ne!(
+            key("nomic-embed-text", "Budget"),
+            key("mxbai-embed-large", "Budget")
+        );
+        assert_ne!(
+            key("nomic-embed-text", "Budget"),
+            key("nomic-embed-text", "Hiring")
+        );
+        assert_ne!(key("ab", "c"), key("a", "bc"));
+        assert_eq!(key("", "").len(), 16);
+    }
+
+    #[test]
+    fn test_round_trips_through_disk() {
+        let path = temp_path("embeddings_round_trip");
+        let mut cache = EmbeddingCache::load(&path);
+        cache.insert("nomic-embed-text", "Budget", vec![0.1, 0.2]);
+        cache.save().unwrap();
+
+        let cache = EmbeddingCache::load(&path);
+        assert_eq!(
+            cache.get("nomic-embed-text", "Budget"),
+            Some(&vec![0.1, 0.2])
+        );
+        assert_eq!(cache.get("nomic-embed-text", "Hiring"), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_other_model_invalidates() {
+        let mut cache = EmbeddingCache::load(&temp_path("embeddings_invalidate"));
+        cache.insert("nomic-embed-text", "Budget", vec![0.1, 0.2]);
+        assert_eq!(cache.get("mxbai-embed-large", "Budget"), None);
+
+        cache.insert("mxbai-embed-large", "Hiring", vec![0.3]);
+        assert_eq!(cache.get("nomic-embed-text", "Budget"), None);
+        assert_eq!(cache.get("mxbai-embed-large", "Hiring"), Some(&vec![0.3]));
+    }
+
+    #[test]
+    fn test_embeds_missing_items_in_one_request() {
+        let path = temp_path("embeddings_fetch");
+        let (base, requests) = mock_server(vec![(
+            200,
+            r#"{"model":"nomic-embed-text","embeddings":[[0.5,0.5],[0.9,0.1]]}"#.to_string(),
+        )]);
+        let cache = Mutex::new(EmbeddingCache::load(&path));
+        cache
+            .lock()
+            .unwrap()
+            .insert("nomic-embed-text", "Budget", vec![0.1, 0.2]);
+        let mut items = vec![
+            item("1", "Budget"),
+            item("2", "Hiring"),
+            item("3", "Launch date"),
+            item("4", "Hiring"),
+        ];
+
+        let missing = cache.lock().unwrap().fill("nomic-embed-text", &mut items);
+        assert_eq!(missing, vec!["Hiring", "Launch date"]);
+        assert_eq!(items[0].embedding, Some(vec![0.1, 0.2]));
+        assert_eq!(items[1].embedding, None);
+
+        EmbeddingCache::fetch(&cache, "nomic-embed-text", &missing, &base).unwrap();
+        assert!(cache
+            .lock()
+            .unwrap()
+            .fill("nomic-embed-text", &mut items)
+            .is_empty());
+        cache.lock().unwrap().save().unwrap();
+        assert_eq!(items[1].embedding, Some(vec![0.5, 0.5]));
+        assert_eq!(items[2].embedding, Some(vec![0.9, 0.1]));
+        assert_eq!(items[3].embedding, Some(vec![0.5, 0.5]));
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /api/embed "));
+        let body: serde_json::Value =
+            serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["model"], "nomic-embed-text");
+        assert_eq!(body["input"], serde_json::json!(["Hiring", "Launch date"]));
+
+        // Everything is cached now, on disk too
+        let mut again = vec![item("5", "Launch date")];
+        assert!(EmbeddingCache::load(&path)
+            .fill("nomic-embed-text", &mut again)
+            .is_empty());
+        assert_eq!(again[0].embedding, Some(vec![0.9, 0.1]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_short_replies() {
+        let (base, _requests) = mock_server(vec![(
+            200,
+            r#"{"model":"nomic-embed-text","embeddings":[[0.5,0.5]]}"#.to_string(),
+        )]);
+        let cache = Mutex::new(EmbeddingCache::load(&temp_path("embeddings_short")));
+        let texts = vec!["Budget".to_string(), "Hiring".to_string()];
+
+        assert!(EmbeddingCache::fetch(&cache, "nomic-embed-text", &texts, &base).is_err());
+        assert_eq!(
+            cache.lock().unwrap().get("nomic-embed-text", "Budget"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retain_drops_texts_off_the_agenda() {
+        let mut cache = EmbeddingCache::load(&temp_path("embeddings_retain"));
+        cache.insert("nomic-embed-text", "Budg", vec![0.1]);
+        cache.insert("nomic-embed-text", "Budget", vec![0.2]);
+        cache.insert("nomic-embed-text", "Hiring", vec![0.3]);
+
+        cache.retain(["Budget", "Hiring", "Launch date"]);
+        assert_eq!(cache.get("nomic-embed-text", "Budg"), None);
+        assert_eq!(cache.get("nomic-embed-text", "Budget"), Some(&vec![0.2]));
+        assert_eq!(cache.get("nomic-embed-text", "Hiring"), Some(&vec![0.3]));
+        assert_eq!(cache.file.vectors.len(), 2);
+    }
+}
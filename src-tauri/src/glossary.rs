@@ -0,0 +1,122 @@
+use std::path::Path;
+
+/// Style the transcript should follow; Whisper imitates the prompt it is given.
+const STYLE_PROMPT: &str = "The following is a high-quality, punctuated transcript of a professional conversation. It includes proper capitalization and ignores filler words like 'um' or 'uh'.";
+
+// Whisper keeps only the last ~224 prompt tokens; stay well inside that
+const MAX_PROMPT_CHARS: usize = 700;
+// Between the style sentence and the first term
+const GLOSSARY_LABEL: &str = " Glossary: ";
+
+/// Reads glossary terms from a file: one term per line, lines starting with `#`
+/// are comments. A `#` anywhere else is part of the term, as in "C#".
+pub fn load(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|content| parse(&content))
+        .unwrap_or_default()
+}
+
+pub fn parse(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn save(path: &Path, terms: &[String]) -> std::io::Result<()> {
+    let mut content = String::from(
+        "# Names, products and acronyms Whisper should spell correctly. One per line.\n",
+    );
+    for term in terms {
+        content.push_str(term);
+        content.push('\n');
+    }
+    std::fs::write(path, content)
+}
+
+/// The initial prompt for Whisper: the style sentence, then as many terms as
+/// fit, earlier terms first. Duplicates are skipped.
+pub fn build_prompt<'a>(terms: impl IntoIterator<Item = &'a str>) -> String {
+    let mut prompt = STYLE_PROMPT.to_string();
+    let mut seen: Vec<String> = Vec::new();
+
+    for term in terms {
+        let term = term.trim().trim_end_matches(['.', ',']);
+        if term.is_empty() || seen.iter().any(|s| s.eq_ignore_ascii_case(term)) {
+            continue;
+        }
+        let separator = if seen.is_empty() {
+            GLOSSARY_LABEL
+        } else {
+            ", "
+        };
+        if prompt.len() + separator.len() + term.len() + 1 > MAX_PROMPT_CHARS {
+            break;
+        }
+        prompt.push_str(separator);
+        prompt.push_str(term);
+        seen.push(term.to_string());
+    }
+
+    if !seen.is_empty() {
+        prompt.push('.');
+    }
+    prompt
+}
+
+/// Splits a prompt made by `build_prompt` back into its style sentence and terms.
+pub fn split_prompt(prompt: &str) -> (&str, Vec<&str>) {
+    match prompt.split_once(GLOSSARY_LABEL) {
+        Some((style, terms)) => (style, terms.trim_end_matches('.').split(", ").collect()),
+        None => (prompt, Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_comments_and_blanks() {
+        let terms = parse("# header\nKuroko\n\n  Ollama  \n  # indented\nBlackHole 2ch\nF#\n");
+        assert_eq!(terms, vec!["Kuroko", "Ollama", "BlackHole 2ch", "F#"]);
+    }
+
+    #[test]
+    fn test_save_round_trips() {
+        let path = std::env::temp_dir().join("kuroko_glossary_test.txt");
+        let terms = vec!["Kuroko".to_string(), "OKR".to_string(), "C#".to_string()];
+        save(&path, &terms).unwrap();
+        assert_eq!(load(&path), terms);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_build_prompt_lists_terms_once() {
+        let prompt = build_prompt(["Kuroko", "OKR", "kuroko", "", "Jacek."]);
+        assert!(prompt.starts_with(STYLE_PROMPT));
+        assert!(prompt.ends_with(" Glossary: Kuroko, OKR, Jacek."));
+        assert_eq!(build_prompt([]), STYLE_PROMPT);
+    }
+
+    #[test]
+    fn test_split_prompt_undoes_build_prompt() {
+        let prompt = build_prompt(["Kuroko", "Agree on the Q3 budget."]);
+        assert_eq!(
+            split_prompt(&prompt),
+            (STYLE_PROMPT, vec!["Kuroko", "Agree on the Q3 budget"])
+        );
+        assert_eq!(split_prompt(STYLE_PROMPT), (STYLE_PROMPT, vec![]));
+    }
+
+    #[test]
+    fn test_build_prompt_stays_within_limit() {
+        let terms: Vec<String> = (0..500).map(|i| format!("Term{}", i)).collect();
+        let prompt = build_prompt(terms.iter().map(String::as_str));
+        assert!(prompt.len() <= MAX_PROMPT_CHARS);
+        assert!(prompt.contains("Term0, Term1"));
+        assert!(prompt.ends_with('.'));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::transcription::TimedSegment;
+use std::path::Path;
+
+// Whisper's own fallback thresholds: above this no-speech probability, or
+// below this mean token log probability, a segment is most likely invented
+const MAX_NO_SPEECH_PROB: f32 = 0.6;
+const MIN_AVG_LOGPROB: f32 = -1.0;
+
+// A phrase repeated this many times in a row is a decoding loop
+const MIN_REPEATS: usize = 3;
+// Longest phrase, in words, checked for repetition
+const MAX_NGRAM: usize = 8;
+
+/// Lines Whisper is known to produce on silence or music, learned from subtitled video.
+pub const DEFAULT_BLOCKLIST: &[&str] = &[
+    "Thank you for watching.",
+    "Thanks for watching!",
+    "Thank you so much for watching.",
+    "Please subscribe to my channel.",
+    "Don't forget to like and subscribe.",
+    "Subtitles by the Amara.org community",
+    "Transcription by CastingWords",
+    "Продолжение следует...",
+    "Dziękuję za uwagę.",
+];
+
+/// Drops segments Whisper made up and cleans up decoding loops in the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HallucinationFilter {
+    /// Whole-segment phrases to drop, compared ignoring case and punctuation
+    blocklist: Vec<String>,
+}
+
+impl HallucinationFilter {
+    pub fn new(blocklist: &[String]) -> Self {
+        HallucinationFilter {
+            blocklist: blocklist
+                .iter()
+                .map(|phrase| normalise(phrase))
+                .filter(|phrase| !phrase.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, segments: Vec<TimedSegment>) -> Vec<TimedSegment> {
+        segments
+            .into_iter()
+            .filter(|segment| {
+                segment.no_speech_prob.unwrap_or(0.0) <= MAX_NO_SPEECH_PROB
+                    && segment.avg_logprob >= MIN_AVG_LOGPROB
+            })
+            .filter_map(|mut segment| {
+                segment.text = collapse_repeats(&segment.text);
+                if let Some(original) = &segment.original_text {
+                    segment.original_text = Some(collapse_repeats(original));
+                }
+                (!segment.text.is_empty() && !self.is_blocked(&segment.text)).then_some(segment)
+            })
+            .collect()
+    }
+
+    fn is_blocked(&self, text: &str) -> bool {
+        self.blocklist.contains(&normalise(text))
+    }
+}
+
+/// Reads the blocklist, one phrase per line; the defaults when there is no file yet.
+pub fn load_blocklist(path: &Path) -> Vec<String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => crate::glossary::parse(&content),
+        Err(_) => DEFAULT_BLOCKLIST.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// Keeps one copy of any phrase of up to `MAX_NGRAM` words that appears
+/// `MIN_REPEATS` or more times in a row, e.g. "I think I think I think so"
+/// becomes "I think so".
+pub fn collapse_repeats(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|word| normalise(word)).collect();
+
+    let mut kept: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        // The repetition covering the most words wins
+        let repeat = (1..=MAX_NGRAM)
+            .map(|n| (n, copies(&keys[i..], n)))
+            .filter(|&(_, copies)| copies >= MIN_REPEATS)
+            .max_by_key(|&(n, copies)| n * copies);
+
+        match repeat {
+            Some((n, copies)) => {
+                // Keep the first copy, closed with the last copy's punctuation
+                let end = i + n * copies;
+                kept.extend(words[i..i + n - 1].iter().map(|w| w.to_string()));
+                kept.push(format!(
+                    "{}{}",
+                    words[i + n - 1].trim_end_matches(|c: char| !c.is_alphanumeric()),
+                    trailing_punctuation(words[end - 1])
+                ));
+                i = end;
+            }
+            None => {
+                kept.push(words[i].to_string());
+                i += 1;
+            }
+        }
+    }
+    kept.join(" ")
+}
+
+fn trailing_punctuation(word: &str) -> &str {
+    &word[word.trim_end_matches(|c: char| !c.is_alphanumeric()).len()..]
+}
+
+// How many times the first `n` words repeat back to back
+fn copies(keys: &[String], n: usize) -> usize {
+    if keys.len() < n {
+        return 0;
+    }
+    keys.chunks_exact(n)
+        .take_while(|chunk| *chunk == &keys[..n])
+        .count()
+}
+
+// Lowercase words without punctuation, single-spaced
+fn normalise(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str) -> TimedSegment {
+        TimedSegment::new(0.0, 2.0, text)
+    }
+
+    fn default_filter() -> HallucinationFilter {
+        let blocklist: Vec<String> = DEFAULT_BLOCKLIST.iter().map(|s| s.to_string()).collect();
+        HallucinationFilter::new(&blocklist)
+    }
+
+    fn texts(segments: &[TimedSegment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_collapse_repeats() {
+        assert_eq!(
+            collapse_repeats("I think I think I think so."),
+            "I think so."
+        );
+        assert_eq!(
+            collapse_repeats("Okay. Okay. Okay. Okay. Okay. Let's start."),
+            "Okay. Let's start."
+        );
+        assert_eq!(
+            collapse_repeats("and then we ship it, and then we ship it, and then we ship it."),
+            "and then we ship it."
+        );
+        // Two copies can be deliberate
+        assert_eq!(
+            collapse_repeats("No, no, that's wrong."),
+            "No, no, that's wrong."
+        );
+        assert_eq!(collapse_repeats(""), "");
+    }
+
+    #[test]
+    fn test_drops_blocklisted_phrases() {
+        let filter = default_filter();
+        let segments = vec![
+            segment(" Thank you for watching."),
+            segment("THANKS FOR WATCHING"),
+            segment("Thanks for watching the demo, everyone."),
+            segment("Thank you for watching. Thank you for watching. Thank you for watching."),
+        ];
+        assert_eq!(
+            texts(&filter.apply(segments)),
+            vec!["Thanks for watching the demo, everyone."]
+        );
+    }
+
+    #[test]
+    fn test_blocklist_is_configurable() {
+        let filter = HallucinationFilter::new(&["Bye bye.".to_string(), " ".to_string()]);
+        let segments = vec![segment("Bye, BYE!"), segment("Thank you for watching.")];
+        assert_eq!(
+            texts(&filter.apply(segments)),
+            vec!["Thank you for watching."]
+        );
+    }
+
+    #[test]
+    fn test_drops_unlikely_segments() {
+        let filter = default_filter();
+        let mut noise = segment("Music playing softly.");
+        noise.no_speech_prob = Some(0.9);
+        let mut garbled = segment("Ah, the fjords of the shed.");
+        garbled.avg_logprob = -1.6;
+        let mut speech = segment("Let's move to the budget.");
+        speech.no_speech_prob = Some(0.1);
+
+        assert_eq!(
+            texts(&filter.apply(vec![noise, garbled, speech])),
+            vec!["Let's move to the budget."]
+        );
+    }
+
+    #[test]
+    fn test_collapses_loops_in_kept_segments() {
+        let filter = default_filter();
+        let mut translated = segment("Yes, yes, yes, yes, we agree.");
+        translated.original_text = Some("Tak, tak, tak, tak, zgadzamy się.".to_string());
+
+        let kept = filter.apply(vec![translated]);
+        assert_eq!(kept[0].text, "Yes, we agree.");
+        assert_eq!(kept[0].original_text.as_deref(), Some("Tak, zgadzamy się."));
+    }
+}
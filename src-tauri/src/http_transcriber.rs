@@ -0,0 +1,485 @@
+use crate::transcription::{
+    attach_originals, preprocess_audio, strip_prompt_echo, DecodeOptions, LanguageMode,
+    TimedSegment, Transcriber,
+};
+use crate::vad::{detect_speech, SpeechAudio};
+use serde::Deserialize;
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 16000;
+// A slow CPU box can take a while over a long window
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Transcribes on a remote server: a whisper.cpp `server` (`/inference`) or
+/// anything speaking OpenAI's `/v1/audio/transcriptions`.
+///
+/// Only the speech found by the VAD is uploaded, as a 16-bit WAV file.
+/// Allow-listed auto-detection cannot be enforced remotely; the server picks
+/// any language it likes.
+pub struct HttpTranscriber {
+    url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+#[derive(Deserialize)]
+struct VerboseResponse {
+    #[serde(default)]
+    text: String,
+    /// A code ("en") or full name ("english"), depending on the server
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<ResponseSegment>,
+}
+
+#[derive(Deserialize)]
+struct ResponseSegment {
+    start: f32,
+    end: f32,
+    text: String,
+    avg_logprob: Option<f32>,
+    no_speech_prob: Option<f32>,
+}
+
+impl HttpTranscriber {
+    pub fn new(url: &str, api_key: Option<String>, model: Option<String>) -> Result<Self, String> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("Transcription server URL is not valid: '{}'", url));
+        }
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(HttpTranscriber {
+            url: url.to_string(),
+            api_key,
+            model,
+            client,
+        })
+    }
+
+    // OpenAI translates on a sibling endpoint; whisper.cpp takes a flag
+    fn translation_url(&self) -> Option<String> {
+        self.url
+            .strip_suffix("/audio/transcriptions")
+            .map(|base| format!("{}/audio/translations", base))
+    }
+
+    fn request(
+        &self,
+        url: &str,
+        wav: &[u8],
+        fields: &[(&str, &str)],
+    ) -> Result<VerboseResponse, String> {
+        let mut form = Multipart::new();
+        form.file("file", "audio.wav", "audio/wav", wav);
+        if let Some(model) = &self.model {
+            form.text("model", model);
+        }
+        form.text("response_format", "verbose_json");
+        for (name, value) in fields {
+            form.text(name, value);
+        }
+        let (content_type, body) = form.finish();
+
+        let mut req = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+
+        let resp = req
+            .send()
+            .map_err(|e| format!("Transcription server unreachable: {}", e))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let detail = resp.text().unwrap_or_default();
+            return Err(format!(
+                "Transcription server failed: {} {}",
+                status,
+                detail.trim()
+            ));
+        }
+        resp.json()
+            .map_err(|e| format!("Unexpected transcription server response: {}", e))
+    }
+}
+
+impl Transcriber for HttpTranscriber {
+    fn transcribe(
+        &self,
+        samples: &[f32],
+        options: &DecodeOptions,
+    ) -> Result<Vec<TimedSegment>, String> {
+        let speech = SpeechAudio::from_spans(samples, &detect_speech(samples, options.threshold));
+        if speech.samples.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut processed_samples = speech.samples.clone();
+        preprocess_audio(&mut processed_samples);
+        let wav = encode_wav(&processed_samples);
+
+        let spoken = options.spoken_language();
+        let fixed = match &spoken {
+            LanguageMode::Fixed(code) => Some(code.as_str()),
+            LanguageMode::Auto { .. } => None,
+        };
+        let mut fields = Vec::new();
+        if let Some(code) = fixed {
+            fields.push(("language", code));
+        }
+        if !options.prompt.is_empty() {
+            fields.push(("prompt", options.prompt.as_str()));
+        }
+
+        let response = self.request(&self.url, &wav, &fields)?;
+        let language = response
+            .language
+            .as_deref()
+            .and_then(language_code)
+            .or_else(|| fixed.map(str::to_string));
+        let mut segments = response.into_segments(&speech, &options.prompt);
+
+        if options.mode == "translate" && language.as_deref() != Some("en") {
+            let translated = match self.translation_url() {
+                Some(url) => {
+                    // The OpenAI translation endpoint takes no language
+                    fields.retain(|(name, _)| *name != "language");
+                    self.request(&url, &wav, &fields)?
+                }
+                None => {
+                    fields.push(("translate", "true"));
+                    self.request(&self.url, &wav, &fields)?
+                }
+            };
+            segments =
+                attach_originals(translated.into_segments(&speech, &options.prompt), segments);
+        }
+
+        for segment in segments.iter_mut() {
+            segment.language = language.clone();
+        }
+        Ok(options.filter.apply(segments))
+    }
+}
+
+impl VerboseResponse {
+    // Segment times come back relative to the uploaded speech-only clip
+    fn into_segments(self, speech: &SpeechAudio, prompt: &str) -> Vec<TimedSegment> {
+        let segments = if self.segments.is_empty() {
+            // Plain `json` answers: one segment over the whole clip
+            vec![ResponseSegment {
+                start: 0.0,
+                end: speech.samples.len() as f32 / SAMPLE_RATE as f32,
+                text: self.text,
+                avg_logprob: None,
+                no_speech_prob: None,
+            }]
+        } else {
+            self.segments
+        };
+
+        segments
+            .into_iter()
+            .map(|s| {
+                // Servers that report no confidence should not trip the filter
+                let avg_logprob = s.avg_logprob.unwrap_or(0.0);
+                TimedSegment {
+                    start_secs: speech.source_secs(s.start),
+                    end_secs: speech.source_secs(s.end),
+                    text: strip_prompt_echo(&s.text, prompt),
+                    original_text: None,
+                    // Geometric rather than arithmetic mean; close enough to rank by
+                    avg_token_prob: avg_logprob.exp(),
+                    avg_logprob,
+                    no_speech_prob: s.no_speech_prob,
+                    language: None,
+                    language_prob: None,
+                }
+            })
+            .filter(|s| !s.text.trim().is_empty())
+            .collect()
+    }
+}
+
+// whisper.cpp and OpenAI report the language as "english"; Whisper knows both forms
+fn language_code(name: &str) -> Option<String> {
+    whisper_rs::get_lang_id(&name.trim().to_lowercase())
+        .and_then(whisper_rs::get_lang_str)
+        .map(str::to_string)
+}
+
+/// 16 kHz mono samples as a 16-bit PCM WAV file.
+fn encode_wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        let value = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+    wav
+}
+
+// A multipart/form-data body; reqwest only builds these behind an extra feature
+struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    fn new() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Multipart {
+            boundary: format!("kuroko-{:x}", nanos),
+            body: Vec::new(),
+        }
+    }
+
+    fn text(&mut self, name: &str, value: &str) {
+        self.part(name, None, value.as_bytes());
+    }
+
+    fn file(&mut self, name: &str, filename: &str, content_type: &str, bytes: &[u8]) {
+        self.part(name, Some((filename, content_type)), bytes);
+    }
+
+    fn part(&mut self, name: &str, file: Option<(&str, &str)>, bytes: &[u8]) {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary, name
+        );
+        if let Some((filename, content_type)) = file {
+            header.push_str(&format!(
+                "; filename=\"{}\"\r\nContent-Type: {}",
+                filename, content_type
+            ));
+        }
+        header.push_str("\r\n\r\n");
+        self.body.extend_from_slice(header.as_bytes());
+        self.body.extend_from_slice(bytes);
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    fn finish(mut self) -> (String, Vec<u8>) {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        (
+            format!("multipart/form-data; boundary={}", self.boundary),
+            self.body,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hallucination::HallucinationFilter;
+    use crate::mock_server::mock_server;
+
+    // One second of silence either side of two seconds of a 220 Hz tone
+    fn tone_clip() -> Vec<f32> {
+        let mut samples = vec![0.0; SAMPLE_RATE as usize];
+        samples.extend((0..2 * SAMPLE_RATE as usize).map(|i| {
+            0.3 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / SAMPLE_RATE as f32).sin()
+        }));
+        samples.extend(vec![0.0; SAMPLE_RATE as usize]);
+        samples
+    }
+
+    fn options(mode: &str, language: LanguageMode) -> DecodeOptions {
+        DecodeOptions {
+            threshold: 0.005,
+            mode: mode.to_string(),
+            language,
+            threads: 4,
+            prompt: "Glossary: Kuroko.".to_string(),
+            filter: HallucinationFilter::new(&["Thank you for watching.".to_string()]),
+        }
+    }
+
+    #[test]
+    fn test_transcribes_verbose_json() {
+        let (base, requests) = mock_server(vec![(
+            200,
+            r#"{"text":"Hello Kuroko.","language":"english","segments":[
+                {"start":0.0,"end":1.0,"text":" Hello Kuroko.","avg_logprob":-0.2,"no_speech_prob":0.01},
+                {"start":1.0,"end":2.0,"text":" Thank you for watching.","avg_logprob":-0.3,"no_speech_prob":0.02},
+                {"start":2.0,"end":2.4,"text":" mumble","avg_logprob":-2.5,"no_speech_prob":0.2}
+            ]}"#
+            .to_string(),
+        )]);
+        let backend = HttpTranscriber::new(
+            &format!("{}/inference", base),
+            Some("secret".to_string()),
+            Some("whisper-1".to_string()),
+        )
+        .unwrap();
+
+        let segments = backend
+            .transcribe(
+                &tone_clip(),
+                &options("speed", LanguageMode::Fixed("en".into())),
+            )
+            .unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text.trim(), "Hello Kuroko.");
+        assert_eq!(segments[0].language.as_deref(), Some("en"));
+        assert_eq!(segments[0].no_speech_prob, Some(0.01));
+        // The clip starts with the VAD padding before the tone
+        assert!((segments[0].start_secs - 0.75).abs() < 0.05);
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /inference "));
+        assert!(request
+            .to_lowercase()
+            .contains("authorization: bearer secret"));
+        assert!(request.contains("name=\"file\"; filename=\"audio.wav\""));
+        assert!(request.contains("RIFF"));
+        assert!(request.contains("name=\"model\"\r\n\r\nwhisper-1\r\n"));
+        assert!(request.contains("name=\"language\"\r\n\r\nen\r\n"));
+        assert!(request.contains("name=\"prompt\"\r\n\r\nGlossary: Kuroko.\r\n"));
+        assert!(request.contains("name=\"response_format\"\r\n\r\nverbose_json\r\n"));
+    }
+
+    #[test]
+    fn test_plain_json_becomes_one_segment() {
+        let (base, _requests) =
+            mock_server(vec![(200, r#"{"text":" Let's get started."}"#.to_string())]);
+        let backend = HttpTranscriber::new(&base, None, None).unwrap();
+
+        let segments = backend
+            .transcribe(
+                &tone_clip(),
+                &options("speed", LanguageMode::Fixed("en".into())),
+            )
+            .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text.trim(), "Let's get started.");
+        assert!(segments[0].end_secs > 2.5);
+    }
+
+    #[test]
+    fn test_translates_on_openai_endpoint() {
+        let (base, requests) = mock_server(vec![
+            (
+                200,
+                r#"{"text":"Dzień dobry.","language":"polish","segments":[{"start":0.0,"end":2.0,"text":"Dzień dobry."}]}"#
+                    .to_string(),
+            ),
+            (
+                200,
+                r#"{"text":"Good morning.","segments":[{"start":0.0,"end":2.0,"text":"Good morning."}]}"#
+                    .to_string(),
+            ),
+        ]);
+        let backend =
+            HttpTranscriber::new(&format!("{}/v1/audio/transcriptions", base), None, None).unwrap();
+
+        let segments = backend
+            .transcribe(
+                &tone_clip(),
+                &options("translate", LanguageMode::Auto { allowed: vec![] }),
+            )
+            .unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "Good morning.");
+        assert_eq!(segments[0].original_text.as_deref(), Some("Dzień dobry."));
+        assert_eq!(segments[0].language.as_deref(), Some("pl"));
+
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("POST /v1/audio/transcriptions "));
+        let translation = requests.recv().unwrap();
+        assert!(translation.starts_with("POST /v1/audio/translations "));
+        assert!(!translation.contains("name=\"language\""));
+    }
+
+    #[test]
+    fn test_translates_with_default_language() {
+        let (base, requests) = mock_server(vec![
+            (
+                200,
+                r#"{"text":"Dzień dobry.","language":"polish","segments":[{"start":0.0,"end":2.0,"text":"Dzień dobry."}]}"#
+                    .to_string(),
+            ),
+            (
+                200,
+                r#"{"text":"Good morning.","segments":[{"start":0.0,"end":2.0,"text":"Good morning."}]}"#
+                    .to_string(),
+            ),
+        ]);
+        let backend = HttpTranscriber::new(&format!("{}/inference", base), None, None).unwrap();
+
+        // WHISPER_LANGUAGE defaults to "en"
+        let segments = backend
+            .transcribe(
+                &tone_clip(),
+                &options("translate", LanguageMode::from_config("en", &[])),
+            )
+            .unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "Good morning.");
+        assert_eq!(segments[0].original_text.as_deref(), Some("Dzień dobry."));
+        assert_eq!(segments[0].language.as_deref(), Some("pl"));
+
+        let transcription = requests.recv().unwrap();
+        assert!(!transcription.contains("name=\"language\""));
+        assert!(!transcription.contains("name=\"translate\""));
+        let translation = requests.recv().unwrap();
+        assert!(translation.starts_with("POST /inference "));
+        assert!(translation.contains("name=\"translate\"\r\n\r\ntrue\r\n"));
+    }
+
+    #[test]
+    fn test_reports_server_errors() {
+        let (base, _requests) =
+            mock_server(vec![(500, r#"{"error":"model not loaded"}"#.to_string())]);
+        let backend = HttpTranscriber::new(&base, None, None).unwrap();
+
+        let err = backend
+            .transcribe(
+                &tone_clip(),
+                &options("speed", LanguageMode::Fixed("en".into())),
+            )
+            .unwrap_err();
+        assert!(err.contains("500"), "{}", err);
+        assert!(err.contains("model not loaded"), "{}", err);
+    }
+
+    #[test]
+    fn test_skips_request_without_speech() {
+        let backend = HttpTranscriber::new("http://127.0.0.1:9", None, None).unwrap();
+        let segments = backend
+            .transcribe(
+                &vec![0.0; 3 * SAMPLE_RATE as usize],
+                &options("speed", LanguageMode::Fixed("en".into())),
+            )
+            .unwrap();
+        assert!(segments.is_empty());
+        assert!(HttpTranscriber::new("localhost:8080", None, None).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// Number of bars in the `buffer-activity` meter.
+pub const METER_BUCKETS: usize = 100;
+
+/// Running per-block energy of a capture channel.
+///
+/// The rolling buffer is divided into `METER_BUCKETS` equal blocks (0.45 s each
+/// for a 45 s buffer). The capture path feeds every converted sample through
+/// `push`, which closes a block's RMS as soon as it fills, so the activity
+/// meter only reads precomputed values instead of rescanning the buffer. The
+/// same pass accumulates the short-term RMS behind `volume-level`.
+///
+/// Like `RingBuffer`, there is a single producer and readers never block it.
+pub struct LevelMeter {
+    bucket_size: usize,
+    // RMS of completed blocks by block number. One spare slot means the block
+    // being written never aliases one a reader is currently showing.
+    levels: Box<[AtomicU32]>,
+    completed: AtomicU64,
+    cleared_at: AtomicU64,
+    clear_pending: AtomicBool,
+    // Producer-side accumulators, f32 sums stored as bits
+    block_sum: AtomicU32,
+    block_len: AtomicUsize,
+    volume_sum: AtomicU32,
+    volume_len: AtomicUsize,
+}
+
+impl LevelMeter {
+    pub fn new(max_samples: usize) -> Self {
+        LevelMeter {
+            bucket_size: max_samples / METER_BUCKETS,
+            levels: (0..=METER_BUCKETS).map(|_| AtomicU32::new(0)).collect(),
+            completed: AtomicU64::new(0),
+            cleared_at: AtomicU64::new(0),
+            clear_pending: AtomicBool::new(false),
+            block_sum: AtomicU32::new(0),
+            block_len: AtomicUsize::new(0),
+            volume_sum: AtomicU32::new(0),
+            volume_len: AtomicUsize::new(0),
+        }
+    }
+
+    /// A meter for a buffer already holding `history`, with bars aligned to its tail.
+    pub fn with_history(max_samples: usize, history: &[f32]) -> Self {
+        let meter = LevelMeter::new(max_samples);
+        let skip = history.len() % meter.bucket_size.max(1);
+        meter.push(&history[skip..]);
+        meter
+    }
+
+    /// Accounts for samples just written to the buffer. Producer only.
+    pub fn push(&self, samples: &[f32]) {
+        if self.clear_pending.swap(false, Ordering::Relaxed) {
+            self.block_sum.store(0, Ordering::Relaxed);
+            self.block_len.store(0, Ordering::Relaxed);
+        }
+
+        let mut block_sum = f32::from_bits(self.block_sum.load(Ordering::Relaxed));
+        let mut block_len = self.block_len.load(Ordering::Relaxed);
+        let mut volume_sum = f32::from_bits(self.volume_sum.load(Ordering::Relaxed));
+
+        for &s in samples {
+            let energy = s * s;
+            volume_sum += energy;
+
+            if self.bucket_size == 0 {
+                continue;
+            }
+            block_sum += energy;
+            block_len += 1;
+            if block_len == self.bucket_size {
+                let block = self.completed.load(Ordering::Relaxed);
+                let level = (block_sum / block_len as f32).sqrt();
+                self.levels[(block % self.levels.len() as u64) as usize]
+                    .store(level.to_bits(), Ordering::Relaxed);
+                self.completed.store(block + 1, Ordering::Release);
+                block_sum = 0.0;
+                block_len = 0;
+            }
+        }
+
+        self.block_sum.store(block_sum.to_bits(), Ordering::Relaxed);
+        self.block_len.store(block_len, Ordering::Relaxed);
+        self.volume_sum
+            .store(volume_sum.to_bits(), Ordering::Relaxed);
+        self.volume_len.fetch_add(samples.len(), Ordering::Relaxed);
+    }
+
+    /// RMS of everything pushed since the previous call. Producer only.
+    pub fn take_volume(&self) -> f32 {
+        let sum = f32::from_bits(self.volume_sum.swap(0, Ordering::Relaxed));
+        let len = self.volume_len.swap(0, Ordering::Relaxed);
+        if len == 0 {
+            0.0
+        } else {
+            (sum / len as f32).sqrt()
+        }
+    }
+
+    /// Levels for the whole buffer window, oldest first.
+    ///
+    /// Follows the buffer's tail: the newest completed block is the last bar,
+    /// and bars older than the recorded history read as silence. Returns
+    /// `None` when the buffer is too short to split into bars.
+    pub fn levels(&self) -> Option<Vec<f32>> {
+        if self.bucket_size == 0 {
+            return None;
+        }
+
+        let completed = self.completed.load(Ordering::Acquire);
+        let cleared_at = self.cleared_at.load(Ordering::Relaxed).min(completed);
+        let available = ((completed - cleared_at) as usize).min(METER_BUCKETS);
+
+        let mut levels = vec![0.0; METER_BUCKETS - available];
+        for block in completed - available as u64..completed {
+            let slot = &self.levels[(block % self.levels.len() as u64) as usize];
+            levels.push(f32::from_bits(slot.load(Ordering::Relaxed)));
+        }
+        Some(levels)
+    }
+
+    /// Forgets the recorded history, alongside `RingBuffer::clear`.
+    pub fn clear(&self) {
+        self.cleared_at
+            .store(self.completed.load(Ordering::Acquire), Ordering::Relaxed);
+        self.clear_pending.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The previous full-rescan computation: buckets laid over the whole
+    // window with the samples we have always forming its tail
+    fn rescan_levels(samples: &[f32], max_samples: usize) -> Vec<f32> {
+        let bucket_size = max_samples / METER_BUCKETS;
+        let virtual_start = max_samples.saturating_sub(samples.len());
+        (0..METER_BUCKETS)
+            .map(|i| {
+                let (start_idx, end_idx) = (i * bucket_size, (i + 1) * bucket_size);
+                if end_idx <= virtual_start {
+                    return 0.0;
+                }
+                let chunk =
+                    &samples[start_idx.saturating_sub(virtual_start)..end_idx - virtual_start];
+                (chunk.iter().map(|&s| s * s).sum::<f32>() / chunk.len() as f32).sqrt()
+            })
+            .collect()
+    }
+
+    fn tail(samples: &[f32], max_samples: usize) -> &[f32] {
+        &samples[samples.len().saturating_sub(max_samples)..]
+    }
+
+    fn assert_levels(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!(
+                (a - e).abs() < 1e-4,
+                "bucket {}: got {}, expected {}",
+                i,
+                a,
+                e
+            );
+        }
+    }
+
+    // Distinct level per block so misalignment shows up
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len).map(|i| ((i / 7) % 13) as f32 * 0.05).collect()
+    }
+
+    #[test]
+    fn test_levels_match_rescan_while_filling() {
+        let max_samples = 1000;
+        let meter = LevelMeter::new(max_samples);
+        let samples = signal(370);
+        // Odd-sized pushes straddle block boundaries
+        for chunk in samples.chunks(17) {
+            meter.push(chunk);
+        }
+        assert_levels(
+            &meter.levels().unwrap(),
+            &rescan_levels(&samples, max_samples),
+        );
+        assert_eq!(meter.levels().unwrap()[..63], [0.0; 63]);
+    }
+
+    #[test]
+    fn test_levels_match_rescan_after_wrapping() {
+        let max_samples = 1000;
+        let meter = LevelMeter::new(max_samples);
+        let samples = signal(3450);
+        for chunk in samples.chunks(33) {
+            meter.push(chunk);
+        }
+        assert_levels(
+            &meter.levels().unwrap(),
+            &rescan_levels(tail(&samples, max_samples), max_samples),
+        );
+    }
+
+    #[test]
+    fn test_levels_match_rescan_mid_block() {
+        let max_samples = 20_000;
+        let bucket_size = max_samples / METER_BUCKETS;
+        // Before and after the buffer wraps, each ending partway into a block
+        for blocks in [40, 130] {
+            let meter = LevelMeter::new(max_samples);
+            let samples = signal(bucket_size * blocks + 137);
+            for chunk in samples.chunks(333) {
+                meter.push(chunk);
+            }
+            // The last bar is the newest completed block; the 137 samples after
+            // it wait for theirs, where the rescan shifted every bar to the tail
+            let completed = &samples[..bucket_size * blocks];
+            assert_levels(
+                &meter.levels().unwrap(),
+                &rescan_levels(tail(completed, max_samples), max_samples),
+            );
+
+            let rest = signal(bucket_size - 137);
+            meter.push(&rest);
+            let samples = [samples, rest].concat();
+            assert_levels(
+                &meter.levels().unwrap(),
+                &rescan_levels(tail(&samples, max_samples), max_samples),
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_history_matches_rescan() {
+        let max_samples = 1000;
+        let history = signal(737);
+        let meter = LevelMeter::with_history(max_samples, &history);
+        assert_levels(
+            &meter.levels().unwrap(),
+            &rescan_levels(&history, max_samples),
+        );
+    }
+
+    #[test]
+    fn test_partial_block_waits_for_completion() {
+        let meter = LevelMeter::new(1000);
+        meter.push(&[0.5; 5]);
+        assert_eq!(meter.levels().unwrap(), vec![0.0; METER_BUCKETS]);
+
+        meter.push(&[0.5; 15]);
+        let levels = meter.levels().unwrap();
+        assert!((levels[METER_BUCKETS - 1] - 0.5).abs() < 1e-6);
+        assert!((levels[METER_BUCKETS - 2] - 0.5).abs() < 1e-6);
+        assert_eq!(levels[METER_BUCKETS - 3], 0.0);
+    }
+
+    #[test]
+    fn test_clear_resets_history_and_partial_block() {
+        let meter = LevelMeter::new(1000);
+        meter.push(&[0.5; 25]);
+        meter.clear();
+        assert_eq!(meter.levels().unwrap(), vec![0.0; METER_BUCKETS]);
+
+        // The 5 leftover samples from before the clear must not count
+        meter.push(&[0.1; 10]);
+        let levels = meter.levels().unwrap();
+        assert!((levels[METER_BUCKETS - 1] - 0.1).abs() < 1e-6);
+        assert_eq!(levels[METER_BUCKETS - 2], 0.0);
+    }
+
+    #[test]
+    fn test_take_volume() {
+        let meter = LevelMeter::new(1000);
+        assert_eq!(meter.take_volume(), 0.0);
+
+        meter.push(&[0.5, -0.5]);
+        meter.push(&[0.5, -0.5]);
+        assert!((meter.take_volume() - 0.5).abs() < 1e-6);
+        assert_eq!(meter.take_volume(), 0.0);
+    }
+
+    #[test]
+    fn test_short_buffer_has_no_levels() {
+        let meter = LevelMeter::new(50);
+        meter.push(&[0.5; 10]);
+        assert!(meter.levels().is_none());
+        assert!((meter.take_volume() - 0.5).abs() < 1e-6);
+    }
+}
@@ -1,9 +1,22 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod audio;
+mod source;
+mod level_meter;
+mod vad;
+mod streaming;
+mod session;
+mod glossary;
+mod embedding_cache;
+mod hallucination;
+mod http_transcriber;
+mod whisper_service;
+pub mod ring_buffer;
 mod agenda;
 mod transcription;
 mod config;
 mod commands;
+#[cfg(test)]
+mod mock_server;
 
 use config::Config;
 
@@ -35,6 +48,12 @@ pub fn run() {
             silence_threshold: 0.005,
             transcription_mode: "speed".to_string(),
             whisper_language: "en".to_string(),
+            whisper_language_allowlist: Vec::new(),
+            whisper_glossary: Vec::new(),
+            whisper_blocklist: hallucination::DEFAULT_BLOCKLIST
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
             agenda_similarity_threshold: 0.35,
             transcription_interval_secs: 5,
             agenda_check_cooldown_secs: 20,
@@ -43,6 +62,15 @@ pub fn run() {
             whisper_threads: 8,
             min_analysis_chars: 25,
             agenda_answered_threshold: 0.95,
+            agenda_batch_size: 8,
+            mic_device: None,
+            capture_mix_mode: "mixed".to_string(),
+            audio_source_file: None,
+            replay_speed: 1.0,
+            transcription_backend: "local".to_string(),
+            transcription_url: "".to_string(),
+            transcription_api_key: None,
+            transcription_model: None,
             error: Some(e),
          };
          c
@@ -125,21 +153,26 @@ pub fn run() {
             commands::get_latest_audio,
             commands::transcribe_audio,
             commands::transcribe_latest,
+            commands::get_session_transcript,
+            commands::get_recent_transcript,
             commands::get_config,
             commands::get_audio_device,
             commands::list_audio_devices,
             commands::set_audio_device,
+            commands::set_audio_source_file,
             commands::log_session,
             commands::hide_window,
             commands::open_config_dir,
             commands::quit_app,
             commands::set_recording_state,
             commands::update_config,
+            commands::reload_whisper_model,
             commands::list_ollama_models,
             commands::validate_gemini_key,
             commands::validate_file_path,
             commands::validate_hotkey,
             commands::update_agenda,
+            commands::set_agenda_status,
             commands::clear_audio_buffer,
             commands::expand_agenda_item
         ])
@@ -0,0 +1,262 @@
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+/// Preallocated rolling sample buffer written from the realtime audio callback.
+///
+/// There is exactly one producer (the capture callback) and any number of
+/// readers. Pushing never blocks or allocates. Readers copy the most recent
+/// samples without taking a lock: the producer announces how far it is about
+/// to write before touching the storage, so a reader can tell which part of
+/// its copy may have been overwritten mid-read and drop it (seqlock style).
+pub struct RingBuffer {
+    // f32 bit patterns, stored atomically so concurrent reads are well defined
+    data: Box<[AtomicU32]>,
+    // Total samples ever committed; the newest sample sits at written - 1
+    written: AtomicU64,
+    // Total samples the producer has started writing (>= written)
+    claimed: AtomicU64,
+    // Position of the last clear(); nothing before it is visible
+    cleared_at: AtomicU64,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RingBuffer {
+            data: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicU64::new(0),
+            claimed: AtomicU64::new(0),
+            cleared_at: AtomicU64::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Appends samples, overwriting the oldest ones once full. Producer only.
+    pub fn push_slice(&self, input: &[f32]) {
+        let capacity = self.data.len();
+        // Only the tail that fits would survive anyway
+        let skipped = input.len().saturating_sub(capacity);
+        let input = &input[skipped..];
+
+        let start = self.written.load(Ordering::Relaxed) + skipped as u64;
+        let end = start + input.len() as u64;
+
+        // Announce the overwrite before doing it
+        self.claimed.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        let offset = (start % capacity as u64) as usize;
+        let first = input.len().min(capacity - offset);
+        for (slot, &s) in self.data[offset..offset + first].iter().zip(input) {
+            slot.store(s.to_bits(), Ordering::Relaxed);
+        }
+        for (slot, &s) in self.data.iter().zip(&input[first..]) {
+            slot.store(s.to_bits(), Ordering::Relaxed);
+        }
+
+        self.written.store(end, Ordering::Release);
+    }
+
+    /// Number of samples currently readable.
+    pub fn len(&self) -> usize {
+        let written = self.written.load(Ordering::Acquire);
+        let cleared_at = self.cleared_at.load(Ordering::Relaxed);
+        (written.saturating_sub(cleared_at) as usize).min(self.data.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of the oldest readable sample.
+    pub fn oldest_position(&self) -> u64 {
+        self.total_written() - self.len() as u64
+    }
+
+    /// Total samples pushed since creation, including ones already rolled out.
+    pub fn total_written(&self) -> u64 {
+        self.written.load(Ordering::Acquire)
+    }
+
+    /// Forgets everything pushed so far.
+    pub fn clear(&self) {
+        let written = self.written.load(Ordering::Acquire);
+        self.cleared_at.store(written, Ordering::Relaxed);
+    }
+
+    /// Copy of the whole readable window, oldest first.
+    pub fn snapshot(&self) -> Vec<f32> {
+        self.snapshot_last(self.data.len())
+    }
+
+    /// Copy of the most recent `n` samples (or fewer, if not yet available), oldest first.
+    pub fn snapshot_last(&self, n: usize) -> Vec<f32> {
+        self.read_tail(n).0
+    }
+
+    /// Everything from position `pos` on (or from the oldest sample still held),
+    /// with the position the copy starts at.
+    pub fn snapshot_from(&self, pos: u64) -> (u64, Vec<f32>) {
+        let n = self.total_written().saturating_sub(pos);
+        let (samples, end) = self.read_tail(n.min(self.data.len() as u64) as usize);
+        (end - samples.len() as u64, samples)
+    }
+
+    /// A new buffer of `capacity` samples holding the most recent audio from
+    /// this one, with sample positions carrying on where this one is.
+    pub fn resized(&self, capacity: usize) -> RingBuffer {
+        let resized = RingBuffer::new(capacity);
+        let (samples, end) = self.read_tail(resized.capacity());
+        let start = end - samples.len() as u64;
+        resized.written.store(start, Ordering::Relaxed);
+        resized.claimed.store(start, Ordering::Relaxed);
+        resized.cleared_at.store(start, Ordering::Relaxed);
+        resized.push_slice(&samples);
+        resized
+    }
+
+    // Tail copy together with the position just past its last sample
+    fn read_tail(&self, n: usize) -> (Vec<f32>, u64) {
+        let capacity = self.data.len() as u64;
+        let end = self.written.load(Ordering::Acquire);
+        let start = end
+            .saturating_sub(n.min(self.data.len()) as u64)
+            .max(self.cleared_at.load(Ordering::Relaxed));
+
+        let mut out = Vec::with_capacity((end - start.min(end)) as usize);
+        let mut pos = start;
+        while pos < end {
+            let offset = (pos % capacity) as usize;
+            let run = ((end - pos) as usize).min(self.data.len() - offset);
+            out.extend(
+                self.data[offset..offset + run]
+                    .iter()
+                    .map(|slot| f32::from_bits(slot.load(Ordering::Relaxed))),
+            );
+            pos += run as u64;
+        }
+
+        // Drop whatever the producer may have overwritten while we were copying
+        fence(Ordering::Acquire);
+        let valid_from = self
+            .claimed
+            .load(Ordering::Relaxed)
+            .saturating_sub(capacity);
+        if valid_from > start {
+            let torn = ((valid_from - start) as usize).min(out.len());
+            out.drain(..torn);
+        }
+
+        (out, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_push_and_snapshot() {
+        let ring = RingBuffer::new(8);
+        ring.push_slice(&[1.0, 2.0, 3.0]);
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.snapshot(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(ring.snapshot_last(2), vec![2.0, 3.0]);
+        assert_eq!(ring.snapshot_last(10), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_wraps_and_keeps_newest() {
+        let ring = RingBuffer::new(4);
+        ring.push_slice(&[1.0, 2.0, 3.0]);
+        ring.push_slice(&[4.0, 5.0, 6.0]);
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.snapshot(), vec![3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(ring.total_written(), 6);
+    }
+
+    #[test]
+    fn test_oversized_push_keeps_tail() {
+        let ring = RingBuffer::new(3);
+        ring.push_slice(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(ring.snapshot(), vec![3.0, 4.0, 5.0]);
+        // Positions still count every sample that came in
+        assert_eq!(ring.total_written(), 5);
+    }
+
+    #[test]
+    fn test_snapshot_from_position() {
+        let ring = RingBuffer::new(4);
+        ring.push_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(ring.oldest_position(), 2);
+        assert_eq!(ring.snapshot_from(4), (4, vec![5.0, 6.0]));
+        // Positions that already rolled out start at the oldest sample
+        assert_eq!(ring.snapshot_from(0), (2, vec![3.0, 4.0, 5.0, 6.0]));
+        assert_eq!(ring.snapshot_from(9), (6, vec![]));
+    }
+
+    #[test]
+    fn test_clear() {
+        let ring = RingBuffer::new(4);
+        ring.push_slice(&[1.0, 2.0]);
+        ring.clear();
+        assert!(ring.is_empty());
+        assert!(ring.snapshot().is_empty());
+        assert_eq!(ring.oldest_position(), 2);
+
+        ring.push_slice(&[7.0]);
+        assert_eq!(ring.snapshot(), vec![7.0]);
+    }
+
+    #[test]
+    fn test_resized_keeps_most_recent_audio() {
+        let ring = RingBuffer::new(6);
+        ring.push_slice(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let shrunk = ring.resized(3);
+        assert_eq!(shrunk.snapshot(), vec![3.0, 4.0, 5.0]);
+        assert_eq!(shrunk.total_written(), 5);
+
+        let grown = ring.resized(10);
+        assert_eq!(grown.snapshot(), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        grown.push_slice(&[6.0]);
+        assert_eq!(grown.len(), 6);
+        assert_eq!(grown.total_written(), 6);
+
+        // Cleared history stays cleared
+        ring.clear();
+        ring.push_slice(&[9.0]);
+        assert_eq!(ring.resized(10).snapshot(), vec![9.0]);
+    }
+
+    #[test]
+    fn test_concurrent_snapshots_are_contiguous() {
+        // The producer writes a ramp; any snapshot must be a run of consecutive values
+        let ring = Arc::new(RingBuffer::new(1024));
+        let producer_ring = ring.clone();
+        let producer = std::thread::spawn(move || {
+            let mut next = 0.0f32;
+            for _ in 0..2000 {
+                let block: Vec<f32> = (0..97)
+                    .map(|_| {
+                        next += 1.0;
+                        next
+                    })
+                    .collect();
+                producer_ring.push_slice(&block);
+            }
+        });
+
+        while !producer.is_finished() {
+            let snap = ring.snapshot();
+            for pair in snap.windows(2) {
+                assert_eq!(pair[1] - pair[0], 1.0);
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(ring.len(), 1024);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::transcription::{Transcript, TranscriptSegment};
+
+/// Everything committed to the transcript since the app started.
+///
+/// The live transcript only covers the rolling audio buffer; once audio rolls
+/// out, its lines end up here instead of disappearing. Only text is kept, never
+/// audio.
+#[derive(Debug, Default)]
+pub struct SessionTranscript {
+    segments: Vec<TranscriptSegment>,
+}
+
+impl SessionTranscript {
+    /// Appends newly committed segments. They arrive in capture order.
+    pub fn extend(&mut self, segments: impl IntoIterator<Item = TranscriptSegment>) {
+        self.segments.extend(segments);
+    }
+
+    /// The whole session so far.
+    pub fn full(&self) -> Transcript {
+        Transcript::new(self.segments.clone())
+    }
+
+    /// Segments still being spoken at or after `since_ms` (Unix epoch milliseconds).
+    pub fn since(&self, since_ms: u64) -> Transcript {
+        // Segments are ordered by start, not end: with separate tracks a long
+        // turn on one can still be running when a later, shorter line has ended
+        Transcript::new(
+            self.segments
+                .iter()
+                .filter(|s| s.end_ms > since_ms)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioRole;
+
+    fn meeting() -> SessionTranscript {
+        let mut session = SessionTranscript::default();
+        session.extend(vec![
+            TranscriptSegment::new(0, 4_000, Some(AudioRole::Loopback), "Welcome everyone."),
+            TranscriptSegment::new(60_000, 65_000, Some(AudioRole::Mic), "Thanks."),
+        ]);
+        session.extend(vec![
+            TranscriptSegment::new(
+                600_000,
+                603_000,
+                Some(AudioRole::Loopback),
+                "Any questions?",
+            ),
+            TranscriptSegment::new(603_000, 605_000, Some(AudioRole::Loopback), "No?"),
+        ]);
+        session
+    }
+
+    #[test]
+    fn test_full_keeps_every_committed_line() {
+        let session = meeting();
+        assert_eq!(session.full().segments.len(), 4);
+        assert_eq!(
+            session.full().text,
+            "Remote: Welcome everyone.\nYou: Thanks.\nRemote: Any questions? No?"
+        );
+    }
+
+    #[test]
+    fn test_since_returns_recent_lines() {
+        let session = meeting();
+        let recent = session.since(300_000);
+        assert_eq!(recent.segments.len(), 2);
+        assert_eq!(recent.text, "Remote: Any questions? No?");
+    }
+
+    #[test]
+    fn test_since_includes_segment_spanning_the_cut() {
+        let session = meeting();
+        let recent = session.since(62_000);
+        assert_eq!(recent.segments[0].text, "Thanks.");
+        assert_eq!(recent.segments.len(), 3);
+    }
+
+    #[test]
+    fn test_since_keeps_overlapping_turns() {
+        let mut session = SessionTranscript::default();
+        session.extend(vec![
+            TranscriptSegment::new(
+                0,
+                10_000,
+                Some(AudioRole::Mic),
+                "Let me walk you through the plan.",
+            ),
+            TranscriptSegment::new(2_000, 4_000, Some(AudioRole::Loopback), "Sure."),
+        ]);
+        let recent = session.since(5_000);
+        assert_eq!(recent.segments.len(), 1);
+        assert_eq!(recent.segments[0].text, "Let me walk you through the plan.");
+    }
+
+    #[test]
+    fn test_since_beyond_end_is_empty() {
+        let session = meeting();
+        assert!(session.since(700_000).segments.is_empty());
+        assert!(SessionTranscript::default().full().text.is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::audio::{create_stream, CaptureSink};
+use cpal::traits::DeviceTrait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Where a capture channel gets its audio from.
+///
+/// A source pushes interleaved, normalised f32 blocks into the `CaptureSink`
+/// it is started with, and keeps doing so until the returned handle is dropped.
+pub trait AudioSource {
+    /// Name shown as the channel's device
+    fn name(&self) -> String;
+    fn start(&self, sink: CaptureSink) -> Result<CaptureHandle, anyhow::Error>;
+}
+
+/// Keeps a started source running; dropping it stops capture.
+pub struct CaptureHandle(#[allow(dead_code)] Box<dyn Send>);
+
+// Wrapper to make cpal::Stream Send/Sync for storage in Mutex
+pub struct SafeStream(#[allow(dead_code)] pub cpal::Stream);
+unsafe impl Send for SafeStream {}
+unsafe impl Sync for SafeStream {}
+
+/// A live input device.
+pub struct CpalSource {
+    device: cpal::Device,
+}
+
+impl CpalSource {
+    pub fn new(device: cpal::Device) -> Self {
+        CpalSource { device }
+    }
+}
+
+impl AudioSource for CpalSource {
+    fn name(&self) -> String {
+        self.device.name().unwrap_or("unknown".to_string())
+    }
+
+    fn start(&self, sink: CaptureSink) -> Result<CaptureHandle, anyhow::Error> {
+        let stream = create_stream(&self.device, sink)?;
+        Ok(CaptureHandle(Box::new(SafeStream(stream))))
+    }
+}
+
+// Replay granularity, roughly one cpal callback worth of audio
+const REPLAY_CHUNK_MS: u64 = 10;
+const KEEP_ALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Replays a recorded WAV or raw PCM file as if it were a device.
+///
+/// `speed` scales playback: 1.0 is real time, 4.0 is four times faster and
+/// 0.0 pushes the whole file as fast as possible. Raw files (anything not
+/// ending in `.wav`) are read as 16 kHz mono signed 16-bit little-endian.
+pub struct FileSource {
+    path: PathBuf,
+    speed: f32,
+}
+
+impl FileSource {
+    pub fn new(path: impl AsRef<Path>, speed: f32) -> Self {
+        FileSource {
+            path: path.as_ref().to_path_buf(),
+            speed: speed.max(0.0),
+        }
+    }
+}
+
+struct FileReplay {
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for FileReplay {
+    // Waits for the replay thread, so the ring buffer's single writer is gone
+    // by the time a new source starts
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl AudioSource for FileSource {
+    fn name(&self) -> String {
+        let file_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("file:{}", file_name)
+    }
+
+    fn start(&self, sink: CaptureSink) -> Result<CaptureHandle, anyhow::Error> {
+        let audio = load_audio_file(&self.path).map_err(|e| anyhow::anyhow!(e))?;
+        println!(
+            "Replaying {:?}: {} Hz, {} channels, {:.1}s at {}x",
+            self.path,
+            audio.sample_rate,
+            audio.channels,
+            audio.duration_secs(),
+            self.speed
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_bg = stop.clone();
+        let speed = self.speed;
+
+        let thread = std::thread::spawn(move || {
+            let mut writer = sink.writer(audio.sample_rate, audio.channels);
+            let frames_per_chunk =
+                ((audio.sample_rate as u64 * REPLAY_CHUNK_MS / 1000) as usize).max(1);
+            let chunk_secs = frames_per_chunk as f64 / audio.sample_rate as f64;
+            let started = std::time::Instant::now();
+
+            for (i, chunk) in audio
+                .samples
+                .chunks(frames_per_chunk * audio.channels)
+                .enumerate()
+            {
+                if stop_bg.load(Ordering::Relaxed) {
+                    return;
+                }
+                writer.write(chunk);
+
+                if speed > 0.0 {
+                    let due = std::time::Duration::from_secs_f64(
+                        (i + 1) as f64 * chunk_secs / speed as f64,
+                    );
+                    if let Some(wait) = due.checked_sub(started.elapsed()) {
+                        std::thread::sleep(wait);
+                    }
+                }
+            }
+            println!("Replay finished");
+
+            // Stay alive so the end of the file doesn't look like a dead device;
+            // short naps keep the join in `FileReplay::drop` quick
+            let mut last_beat = std::time::Instant::now();
+            writer.keep_alive();
+            while !stop_bg.load(Ordering::Relaxed) {
+                if last_beat.elapsed() >= KEEP_ALIVE_INTERVAL {
+                    writer.keep_alive();
+                    last_beat = std::time::Instant::now();
+                }
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+        });
+
+        Ok(CaptureHandle(Box::new(FileReplay {
+            stop,
+            thread: Some(thread),
+        })))
+    }
+}
+
+/// Interleaved samples normalised to [-1.0, 1.0].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedAudio {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub samples: Vec<f32>,
+}
+
+impl DecodedAudio {
+    pub fn duration_secs(&self) -> f32 {
+        let frames = self.samples.len() / self.channels.max(1);
+        frames as f32 / self.sample_rate.max(1) as f32
+    }
+}
+
+pub fn load_audio_file(path: &Path) -> Result<DecodedAudio, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let is_wav = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("wav"))
+        .unwrap_or(false);
+
+    if is_wav {
+        decode_wav(&bytes)
+    } else {
+        Ok(decode_raw_pcm(&bytes))
+    }
+}
+
+/// Headerless 16 kHz mono signed 16-bit little-endian PCM.
+pub fn decode_raw_pcm(bytes: &[u8]) -> DecodedAudio {
+    DecodedAudio {
+        sample_rate: 16000,
+        channels: 1,
+        samples: bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+    }
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Decodes integer (8/16/24/32-bit) and float (32/64-bit) RIFF/WAVE data.
+pub fn decode_wav(bytes: &[u8]) -> Result<DecodedAudio, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format: Option<(u16, usize, u32, u16)> = None;
+    let mut data: Option<&[u8]> = None;
+
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([
+            bytes[pos + 4],
+            bytes[pos + 5],
+            bytes[pos + 6],
+            bytes[pos + 7],
+        ]) as usize;
+        let body_start = pos + 8;
+        // Recorders that crash mid-write leave a data size larger than the file
+        let body_end = (body_start + size).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => {
+                if body.len() < 16 {
+                    return Err("Truncated fmt chunk".to_string());
+                }
+                let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]) as usize;
+                let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                    // The sub-format GUID starts with the actual format tag
+                    tag = u16::from_le_bytes([body[24], body[25]]);
+                }
+                format = Some((tag, channels, sample_rate, bits));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        pos = body_start + size + (size & 1);
+    }
+
+    let (tag, channels, sample_rate, bits) = format.ok_or("Missing fmt chunk")?;
+    let data = data.ok_or("Missing data chunk")?;
+    if channels == 0 || sample_rate == 0 {
+        return Err("Invalid WAV format".to_string());
+    }
+
+    let samples: Vec<f32> = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        (WAVE_FORMAT_PCM, 16) => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 24) => data
+            .chunks_exact(3)
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 32) => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0)
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => data
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32)
+            .collect(),
+        _ => {
+            return Err(format!(
+                "Unsupported WAV encoding (format {}, {} bits)",
+                tag, bits
+            ))
+        }
+    };
+
+    Ok(DecodedAudio {
+        sample_rate,
+        channels,
+        samples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(tag: u16, channels: u16, sample_rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn assert_samples(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "got {}, expected {}", a, e);
+        }
+    }
+
+    #[test]
+    fn test_decode_wav_pcm16_stereo() {
+        let data: Vec<u8> = [0i16, 16384, -32768, 32767]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let audio = decode_wav(&wav_bytes(1, 2, 44100, 16, &data)).unwrap();
+        assert_eq!(audio.sample_rate, 44100);
+        assert_eq!(audio.channels, 2);
+        assert_samples(&audio.samples, &[0.0, 0.5, -1.0, 32767.0 / 32768.0]);
+    }
+
+    #[test]
+    fn test_decode_wav_pcm8_and_24() {
+        let audio = decode_wav(&wav_bytes(1, 1, 8000, 8, &[0, 128, 192])).unwrap();
+        assert_samples(&audio.samples, &[-1.0, 0.0, 0.5]);
+
+        // -0.5 and 0.25 as little-endian 24-bit
+        let data = [0x00, 0x00, 0xC0, 0x00, 0x00, 0x20];
+        let audio = decode_wav(&wav_bytes(1, 1, 48000, 24, &data)).unwrap();
+        assert_samples(&audio.samples, &[-0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_decode_wav_float32() {
+        let data: Vec<u8> = [0.25f32, -0.75]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let audio = decode_wav(&wav_bytes(3, 1, 16000, 32, &data)).unwrap();
+        assert_samples(&audio.samples, &[0.25, -0.75]);
+        assert!((audio.duration_secs() - 2.0 / 16000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_decode_wav_skips_unknown_chunks() {
+        let mut bytes = wav_bytes(1, 1, 16000, 16, &16384i16.to_le_bytes());
+        // Odd-sized LIST chunk before fmt, including its pad byte
+        let list = [b'L', b'I', b'S', b'T', 3, 0, 0, 0, b'a', b'b', b'c', 0];
+        bytes.splice(12..12, list);
+        let audio = decode_wav(&bytes).unwrap();
+        assert_samples(&audio.samples, &[0.5]);
+    }
+
+    #[test]
+    fn test_decode_wav_rejects_bad_input() {
+        assert!(decode_wav(b"not a wav file").is_err());
+        assert!(decode_wav(&wav_bytes(1, 1, 16000, 12, &[0, 0])).is_err());
+
+        let mut missing_data = wav_bytes(1, 1, 16000, 16, &[]);
+        missing_data.truncate(36);
+        assert!(decode_wav(&missing_data).is_err());
+    }
+
+    #[test]
+    fn test_decode_raw_pcm() {
+        let bytes: Vec<u8> = [0i16, -16384, 32767]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .chain([0xFF]) // trailing odd byte is ignored
+            .collect();
+        let audio = decode_raw_pcm(&bytes);
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_samples(&audio.samples, &[0.0, -0.5, 32767.0 / 32768.0]);
+    }
+
+    #[test]
+    fn test_load_audio_file_by_extension() {
+        let dir = std::env::temp_dir();
+        let wav_path = dir.join("kuroko_source_test.wav");
+        let raw_path = dir.join("kuroko_source_test.pcm");
+        let data = 8192i16.to_le_bytes();
+        std::fs::write(&wav_path, wav_bytes(1, 1, 22050, 16, &data)).unwrap();
+        std::fs::write(&raw_path, data).unwrap();
+
+        let wav = load_audio_file(&wav_path).unwrap();
+        let raw = load_audio_file(&raw_path).unwrap();
+        assert_eq!(wav.sample_rate, 22050);
+        assert_eq!(raw.sample_rate, 16000);
+        assert_samples(&wav.samples, &[0.25]);
+        assert_samples(&raw.samples, &[0.25]);
+
+        let _ = std::fs::remove_file(wav_path);
+        let _ = std::fs::remove_file(raw_path);
+        assert!(load_audio_file(&dir.join("kuroko_missing.wav")).is_err());
+    }
+
+    #[test]
+    fn test_file_source_name() {
+        let source = FileSource::new("/tmp/meetings/standup.wav", 1.0);
+        assert_eq!(source.name(), "file:standup.wav");
+    }
+}
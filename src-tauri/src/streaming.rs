@@ -0,0 +1,450 @@
+use crate::transcription::{Transcript, TranscriptSegment};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+const SAMPLE_RATE: u64 = 16000;
+// Already-committed audio decoded again at the start of each pass, so Whisper
+// has context for the first new words
+const OVERLAP_SAMPLES: u64 = SAMPLE_RATE;
+// Segments ending this close to the newest audio may still change next pass
+const STABLE_MARGIN_SAMPLES: u64 = SAMPLE_RATE * 3 / 2;
+
+/// A transcribed segment placed on the capture timeline.
+///
+/// Positions count 16 kHz samples on the loopback channel since capture
+/// started, so they stay valid as the rolling buffer moves on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamSegment {
+    pub start: u64,
+    pub end: u64,
+    pub segment: TranscriptSegment,
+}
+
+/// Builds the live transcript pass by pass from new audio only.
+///
+/// Each pass decodes from shortly before the commit point up to the newest
+/// audio. Segments that end well before the newest audio are committed and
+/// never revisited; the rest are tentative and replaced by the next pass. The
+/// transcript covers what is still in the buffer, so it grows as people speak
+/// and old lines roll off the top with their audio.
+#[derive(Debug, Default)]
+pub struct StreamingTranscriber {
+    committed: Vec<StreamSegment>,
+    tentative: Vec<StreamSegment>,
+    // Everything before this position has been transcribed for good
+    commit_pos: u64,
+}
+
+impl StreamingTranscriber {
+    /// Position the next pass should start decoding from.
+    pub fn resume_from(&self) -> u64 {
+        self.commit_pos.saturating_sub(OVERLAP_SAMPLES)
+    }
+
+    /// Whether the last pass left text that still needs confirming.
+    pub fn has_tentative(&self) -> bool {
+        !self.tentative.is_empty()
+    }
+
+    /// Forgets lines whose audio has left the buffer (rolled out or cleared).
+    pub fn prune(&mut self, buffer_start: u64) {
+        self.committed.retain(|s| s.end > buffer_start);
+        self.tentative.retain(|s| s.end > buffer_start);
+        self.commit_pos = self.commit_pos.max(buffer_start);
+    }
+
+    /// Folds in the segments decoded from `[window_start, window_end)` and
+    /// returns the ones that were newly committed.
+    pub fn update(
+        &mut self,
+        window_start: u64,
+        window_end: u64,
+        segments: Vec<StreamSegment>,
+    ) -> Vec<StreamSegment> {
+        self.commit_pos = self.commit_pos.max(window_start);
+        let stable_until = window_end.saturating_sub(STABLE_MARGIN_SAMPLES);
+
+        self.tentative.clear();
+        let mut newly_committed = Vec::new();
+        for segment in segments {
+            if segment.segment.text.trim().is_empty() {
+                continue;
+            }
+            // Re-decoded overlap that an earlier pass already committed
+            if (segment.start + segment.end) / 2 < self.commit_pos {
+                continue;
+            }
+
+            // Keep order: once one segment is tentative, so is everything after it
+            if segment.end <= stable_until && self.tentative.is_empty() {
+                self.commit_pos = self.commit_pos.max(segment.end);
+                newly_committed.push(segment.clone());
+                self.committed.push(segment);
+            } else {
+                self.tentative.push(segment);
+            }
+        }
+
+        // Nothing pending: the settled part of the window had no more speech
+        if self.tentative.is_empty() {
+            self.commit_pos = self.commit_pos.max(stable_until);
+        }
+
+        newly_committed
+    }
+
+    /// Committed lines followed by the tentative ones.
+    pub fn transcript(&self) -> Transcript {
+        Transcript::new(
+            self.committed
+                .iter()
+                .chain(&self.tentative)
+                .map(|s| s.segment.clone())
+                .collect(),
+        )
+    }
+}
+
+/// Locks `streamer`, taking it back from a pass that panicked while holding it.
+/// Whatever that pass left tentative is dropped; committed lines stand, so the
+/// next pass neither repeats them nor reaches the session twice.
+pub fn lock_streamer(
+    streamer: &Mutex<StreamingTranscriber>,
+) -> MutexGuard<'_, StreamingTranscriber> {
+    streamer.lock().unwrap_or_else(|poisoned| {
+        streamer.clear_poison();
+        let mut streamer = poisoned.into_inner();
+        eprintln!("[Transcription] Recovering the live transcript after a failed pass");
+        streamer.tentative.clear();
+        streamer
+    })
+}
+
+/// Makes overlapping callers share one run of a pass.
+///
+/// Whoever arrives while a run is in flight waits for it and gets its result,
+/// instead of queueing a second run right behind it. A manual request made
+/// mid-pass then costs no extra decode, and sees the audio the pass took.
+/// A run that panics ends with an error for everyone who joined it.
+pub struct Coalescer<T> {
+    state: Mutex<CoalescerState<T>>,
+    finished: Condvar,
+}
+
+struct CoalescerState<T> {
+    running: bool,
+    // Counts finished runs, so waiters can tell theirs has ended
+    runs: u64,
+    last: Option<Result<T, String>>,
+}
+
+// Ends the run when dropped, so a panicking pass still releases its waiters
+struct Run<'a, T> {
+    coalescer: &'a Coalescer<T>,
+    result: Option<Result<T, String>>,
+}
+
+impl<T> Drop for Run<'_, T> {
+    fn drop(&mut self) {
+        // The lock is not held during the pass, but never panic while unwinding
+        let mut state = self
+            .coalescer
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.running = false;
+        state.runs += 1;
+        state.last = Some(
+            self.result
+                .take()
+                .unwrap_or_else(|| Err("The transcription pass panicked".to_string())),
+        );
+        self.coalescer.finished.notify_all();
+    }
+}
+
+impl<T: Clone> Default for Coalescer<T> {
+    fn default() -> Self {
+        Coalescer {
+            state: Mutex::new(CoalescerState {
+                running: false,
+                runs: 0,
+                last: None,
+            }),
+            finished: Condvar::new(),
+        }
+    }
+}
+
+impl<T: Clone> Coalescer<T> {
+    /// Runs `pass`, or joins the run already in flight.
+    pub fn run_or_join(&self, pass: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+        let mut state = self.state.lock().unwrap();
+        if state.running {
+            let joined = state.runs;
+            while state.runs == joined {
+                state = self.finished.wait(state).unwrap();
+            }
+            return state
+                .last
+                .clone()
+                .expect("a finished run leaves its result");
+        }
+        state.running = true;
+        drop(state);
+
+        let mut run = Run {
+            coalescer: self,
+            result: None,
+        };
+        let result = pass();
+        run.result = Some(result.clone());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioRole;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    fn at(secs: f32) -> u64 {
+        (secs * SAMPLE_RATE as f32) as u64
+    }
+
+    fn seg(start_secs: f32, end_secs: f32, role: Option<AudioRole>, text: &str) -> StreamSegment {
+        StreamSegment {
+            start: at(start_secs),
+            end: at(end_secs),
+            segment: TranscriptSegment::new(
+                (start_secs * 1000.0) as u64,
+                (end_secs * 1000.0) as u64,
+                role,
+                text,
+            ),
+        }
+    }
+
+    #[test]
+    fn test_coalescer_joins_run_in_flight() {
+        let coalescer = Arc::new(Coalescer::default());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        let background = {
+            let (coalescer, runs) = (coalescer.clone(), runs.clone());
+            std::thread::spawn(move || {
+                coalescer.run_or_join(|| {
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Ok(runs.fetch_add(1, Ordering::SeqCst) + 1)
+                })
+            })
+        };
+        started.recv().unwrap();
+
+        let manual = {
+            let (coalescer, runs) = (coalescer.clone(), runs.clone());
+            std::thread::spawn(move || {
+                coalescer.run_or_join(|| Ok(runs.fetch_add(1, Ordering::SeqCst) + 1))
+            })
+        };
+        // Give the manual request time to start waiting
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        release.send(()).unwrap();
+
+        assert_eq!(background.join().unwrap(), Ok(1));
+        assert_eq!(manual.join().unwrap(), Ok(1));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // With nothing in flight, a request runs on its own
+        assert_eq!(
+            coalescer.run_or_join(|| Ok(runs.fetch_add(1, Ordering::SeqCst) + 1)),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_coalescer_survives_panicking_run() {
+        let coalescer = Arc::new(Coalescer::<usize>::default());
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        let background = {
+            let coalescer = coalescer.clone();
+            std::thread::spawn(move || {
+                coalescer.run_or_join(|| {
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    panic!("decoder crashed");
+                })
+            })
+        };
+        started.recv().unwrap();
+
+        let manual = {
+            let coalescer = coalescer.clone();
+            std::thread::spawn(move || coalescer.run_or_join(|| Ok(1)))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        release.send(()).unwrap();
+
+        assert!(background.join().is_err());
+        assert!(manual.join().unwrap().is_err());
+        // The next request is not stuck behind the crashed run
+        assert_eq!(coalescer.run_or_join(|| Ok(2)), Ok(2));
+    }
+
+    #[test]
+    fn test_streamer_recovers_from_panicking_pass() {
+        let streamer = Arc::new(Mutex::new(StreamingTranscriber::default()));
+        streamer.lock().unwrap().update(
+            0,
+            at(10.0),
+            vec![
+                seg(0.0, 2.0, None, "Settled."),
+                seg(9.0, 10.0, None, "Still"),
+            ],
+        );
+
+        let crashed = {
+            let streamer = streamer.clone();
+            std::thread::spawn(move || {
+                let _guard = streamer.lock().unwrap();
+                panic!("decoder crashed");
+            })
+        };
+        assert!(crashed.join().is_err());
+        assert!(streamer.is_poisoned());
+
+        let recovered = lock_streamer(&streamer);
+        assert!(!recovered.has_tentative());
+        assert_eq!(recovered.transcript().text, "Settled.");
+        drop(recovered);
+        assert!(!streamer.is_poisoned());
+    }
+
+    #[test]
+    fn test_commits_settled_segments_and_keeps_tail_tentative() {
+        let mut stream = StreamingTranscriber::default();
+        let committed = stream.update(
+            0,
+            at(5.0),
+            vec![
+                seg(0.5, 2.0, None, "Good morning."),
+                seg(2.0, 4.2, None, "Let's look at"),
+            ],
+        );
+
+        assert_eq!(committed.len(), 1);
+        assert_eq!(committed[0].segment.text, "Good morning.");
+        assert!(stream.has_tentative());
+        assert_eq!(stream.transcript().text, "Good morning. Let's look at");
+        // Next pass restarts a second before the end of the committed text
+        assert_eq!(stream.resume_from(), at(1.0));
+    }
+
+    #[test]
+    fn test_next_pass_stitches_without_duplicates() {
+        let mut stream = StreamingTranscriber::default();
+        stream.update(
+            0,
+            at(5.0),
+            vec![
+                seg(0.5, 2.0, None, "Good morning."),
+                seg(2.0, 4.2, None, "Let's look at"),
+            ],
+        );
+
+        // The overlap re-decodes the committed sentence; it must not repeat
+        let committed = stream.update(
+            at(1.0),
+            at(10.0),
+            vec![
+                seg(1.0, 2.0, None, "morning."),
+                seg(2.0, 4.6, None, "Let's look at the numbers."),
+                seg(4.6, 9.5, None, "Revenue is"),
+            ],
+        );
+
+        assert_eq!(committed.len(), 1);
+        assert_eq!(
+            stream.transcript().text,
+            "Good morning. Let's look at the numbers. Revenue is"
+        );
+        assert_eq!(stream.resume_from(), at(3.6));
+    }
+
+    #[test]
+    fn test_committed_text_only_grows() {
+        let mut stream = StreamingTranscriber::default();
+        stream.update(0, at(6.0), vec![seg(0.0, 3.0, None, "One.")]);
+        let first = stream.transcript().text;
+
+        // A later pass that hears nothing new leaves the committed text alone
+        stream.update(stream.resume_from(), at(9.0), vec![]);
+        assert_eq!(stream.transcript().text, first);
+
+        stream.update(
+            stream.resume_from(),
+            at(14.0),
+            vec![seg(9.5, 11.0, None, "Two.")],
+        );
+        assert!(stream.transcript().text.starts_with(&first));
+        assert_eq!(stream.transcript().text, "One. Two.");
+    }
+
+    #[test]
+    fn test_silence_moves_commit_point() {
+        let mut stream = StreamingTranscriber::default();
+        stream.update(0, at(20.0), vec![]);
+        assert!(!stream.has_tentative());
+        assert_eq!(stream.resume_from(), at(17.5));
+    }
+
+    #[test]
+    fn test_prune_drops_lines_that_left_the_buffer() {
+        let mut stream = StreamingTranscriber::default();
+        stream.update(
+            0,
+            at(20.0),
+            vec![
+                seg(1.0, 3.0, None, "Old news."),
+                seg(10.0, 12.0, None, "Recent."),
+            ],
+        );
+
+        stream.prune(at(5.0));
+        assert_eq!(stream.transcript().text, "Recent.");
+
+        // A cleared buffer starts past everything
+        stream.prune(at(30.0));
+        assert_eq!(stream.transcript().text, "");
+        assert_eq!(stream.resume_from(), at(29.0));
+    }
+
+    #[test]
+    fn test_text_labels_speakers() {
+        let mut stream = StreamingTranscriber::default();
+        stream.update(
+            0,
+            at(10.0),
+            vec![
+                seg(0.0, 2.0, Some(AudioRole::Loopback), "Can you hear me?"),
+                seg(2.0, 3.0, Some(AudioRole::Mic), "Yes."),
+                seg(3.0, 4.0, Some(AudioRole::Mic), "Loud and clear."),
+            ],
+        );
+        let transcript = stream.transcript();
+        assert_eq!(
+            transcript.text,
+            "Remote: Can you hear me?\nYou: Yes. Loud and clear."
+        );
+        // Segments stay separate, with their own times and speakers
+        assert_eq!(transcript.segments.len(), 3);
+        assert_eq!(transcript.segments[1].role, Some(AudioRole::Mic));
+        assert_eq!(transcript.segments[1].start_ms, 2000);
+    }
+}
@@ -1,13 +1,324 @@
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+use crate::audio::{format_transcript, AudioRole};
+use crate::config::Config;
+use crate::hallucination::HallucinationFilter;
+use crate::http_transcriber::HttpTranscriber;
+use crate::vad::{detect_speech, SpeechAudio};
+use crate::whisper_service::WhisperService;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
 
+/// Decoded text with its position in the input audio, in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedSegment {
+    pub start_secs: f32,
+    pub end_secs: f32,
+    pub text: String,
+    /// What was actually said, when `text` is a translation of it
+    pub original_text: Option<String>,
+    /// Mean probability Whisper gave the segment's text tokens
+    pub avg_token_prob: f32,
+    /// Mean log probability of the same tokens
+    pub avg_logprob: f32,
+    /// Probability that the segment holds no speech at all, when the decoder
+    /// reports it. The whisper.cpp bundled with whisper-rs 0.11 does not.
+    pub no_speech_prob: Option<f32>,
+    /// Language code the segment was decoded in
+    pub language: Option<String>,
+    /// How sure detection was of `language`; unset when the language was fixed
+    pub language_prob: Option<f32>,
+}
+
+/// One transcript line placed in wall-clock time.
+///
+/// `start_ms` and `end_ms` are milliseconds since the Unix epoch, counted from
+/// when the audio was captured rather than when it was transcribed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Speaker, when a microphone track lets us tell them apart
+    pub role: Option<AudioRole>,
+    pub text: String,
+    pub original_text: Option<String>,
+    pub avg_token_prob: f32,
+    pub no_speech_prob: Option<f32>,
+    pub language: Option<String>,
+    pub language_prob: Option<f32>,
+}
+
+#[cfg(test)]
+impl TimedSegment {
+    /// A confidently decoded segment with nothing else known about it.
+    pub(crate) fn new(start_secs: f32, end_secs: f32, text: &str) -> Self {
+        TimedSegment {
+            start_secs,
+            end_secs,
+            text: text.to_string(),
+            original_text: None,
+            avg_token_prob: 0.9,
+            avg_logprob: -0.1,
+            no_speech_prob: None,
+            language: None,
+            language_prob: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl TranscriptSegment {
+    /// A confidently decoded transcript line with nothing else known about it.
+    pub(crate) fn new(start_ms: u64, end_ms: u64, role: Option<AudioRole>, text: &str) -> Self {
+        TranscriptSegment {
+            start_ms,
+            end_ms,
+            role,
+            text: text.to_string(),
+            original_text: None,
+            avg_token_prob: 0.9,
+            no_speech_prob: None,
+            language: None,
+            language_prob: None,
+        }
+    }
+}
+
+/// Which language Whisper decodes in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LanguageMode {
+    /// Always this language code
+    Fixed(String),
+    /// Detected on every chunk; when `allowed` is not empty, only among those codes
+    Auto { allowed: Vec<String> },
+}
+
+impl LanguageMode {
+    /// `whisper_language` is either a language code or "auto".
+    pub fn from_config(language: &str, allowed: &[String]) -> Self {
+        if language.trim().eq_ignore_ascii_case("auto") {
+            LanguageMode::Auto {
+                allowed: allowed
+                    .iter()
+                    .map(|code| code.trim().to_lowercase())
+                    .filter(|code| !code.is_empty())
+                    .collect(),
+            }
+        } else {
+            LanguageMode::Fixed(language.trim().to_string())
+        }
+    }
+}
+
+/// How to run Whisper, read from the live settings before each pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeOptions {
+    /// Speech gate threshold for the VAD
+    pub threshold: f32,
+    /// "speed", "accuracy" or "translate"
+    pub mode: String,
+    pub language: LanguageMode,
+    pub threads: usize,
+    /// Initial prompt, see `glossary::build_prompt`
+    pub prompt: String,
+    pub filter: HallucinationFilter,
+}
+
+impl DecodeOptions {
+    /// The language to decode in. Translating speech fixed as English would be a
+    /// no-op, so "translate" mode detects the language instead of taking the
+    /// default `en`.
+    pub fn spoken_language(&self) -> LanguageMode {
+        match &self.language {
+            LanguageMode::Fixed(code)
+                if self.mode == "translate" && code.eq_ignore_ascii_case("en") =>
+            {
+                LanguageMode::Auto {
+                    allowed: Vec::new(),
+                }
+            }
+            language => language.clone(),
+        }
+    }
+}
+
+/// Rendered transcript text together with the segments behind it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+impl Transcript {
+    pub fn new(segments: Vec<TranscriptSegment>) -> Self {
+        let text = format_transcript(segments.iter().map(|s| (s.role, s.text.as_str())));
+        Transcript { text, segments }
+    }
+}
+
+/// Turns 16 kHz mono audio into timed text.
+pub trait Transcriber: Send + Sync {
+    /// Segment times are relative to the start of `samples`. The options'
+    /// speech gate, prompt and hallucination filter apply whatever the backend.
+    fn transcribe(
+        &self,
+        samples: &[f32],
+        options: &DecodeOptions,
+    ) -> Result<Vec<TimedSegment>, String>;
+}
+
+/// Where transcription runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendConfig {
+    /// whisper-rs in process, with this ggml model
+    Local { model_path: String },
+    /// A whisper.cpp server or an OpenAI-compatible `audio/transcriptions` endpoint
+    Http {
+        url: String,
+        api_key: Option<String>,
+        model: Option<String>,
+    },
+}
+
+impl BackendConfig {
+    /// `transcription_backend` is "local" (the default) or "http".
+    pub fn from_config(config: &Config) -> Self {
+        if config
+            .transcription_backend
+            .trim()
+            .eq_ignore_ascii_case("http")
+        {
+            BackendConfig::Http {
+                url: config.transcription_url.trim().to_string(),
+                api_key: config
+                    .transcription_api_key
+                    .clone()
+                    .filter(|k| !k.is_empty()),
+                model: config.transcription_model.clone().filter(|m| !m.is_empty()),
+            }
+        } else {
+            BackendConfig::Local {
+                model_path: config.whisper_ggml_path.clone(),
+            }
+        }
+    }
+
+    /// The model path or server URL, for status messages.
+    pub fn location(&self) -> &str {
+        match self {
+            BackendConfig::Local { model_path } => model_path,
+            BackendConfig::Http { url, .. } => url,
+        }
+    }
+}
+
+/// Sets up the configured backend; a local model must pass a test decode first.
+pub fn load_backend(backend: &BackendConfig) -> Result<Arc<dyn Transcriber>, String> {
+    match backend {
+        BackendConfig::Local { model_path } => {
+            Ok(Arc::new(WhisperService::spawn(load_model(model_path)?)?))
+        }
+        BackendConfig::Http {
+            url,
+            api_key,
+            model,
+        } => Ok(Arc::new(HttpTranscriber::new(
+            url,
+            api_key.clone(),
+            model.clone(),
+        )?)),
+    }
+}
+
+/// Loads a ggml model and checks that it can decode before it is put to use.
+pub fn load_model(path: &str) -> Result<WhisperContext, String> {
+    if !std::path::Path::new(path).exists() {
+        return Err(format!("Whisper model not found: {}", path));
+    }
+
+    let ctx = WhisperContext::new_with_params(path, WhisperContextParameters::default())
+        .map_err(|e| format!("Failed to load whisper model: {}", e))?;
+
+    // A second of silence is enough to exercise the whole decode path
+    {
+        let mut state = ctx.create_state().map_err(|e| e.to_string())?;
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        state
+            .full(params, &[0.0; 16000])
+            .map_err(|e| format!("Whisper model failed a test decode: {}", e))?;
+    }
+
+    Ok(ctx)
+}
+
+/// Transcribes 16 kHz mono audio into segments with their timestamps.
+///
+/// In "translate" mode the segments come out in English, each carrying the
+/// text as spoken in `original_text`. Speech already in English is decoded once.
+/// `state` must belong to `ctx`; it is reset by every decode, so one can serve
+/// any number of calls.
 pub fn run_transcription(
     ctx: &WhisperContext,
+    state: &mut WhisperState,
     samples: &[f32],
-    threshold: f32,
-    mode: &str,
-    language: &str,
-    threads: usize,
-) -> Result<String, String> {
+    options: &DecodeOptions,
+) -> Result<Vec<TimedSegment>, String> {
+    let DecodeOptions {
+        threshold,
+        mode,
+        threads,
+        prompt,
+        filter,
+        ..
+    } = options;
+    let threads = *threads;
+
+    if samples.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Voice activity detection: only the spoken parts go to Whisper
+    let speech = SpeechAudio::from_spans(samples, &detect_speech(samples, *threshold));
+    if speech.samples.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Pre-process audio: DC offset removal and Peak Normalization
+    let mut processed_samples = speech.samples.clone();
+    preprocess_audio(&mut processed_samples);
+
+    // Language setting: fixed, or detected from this chunk
+    let (language, language_prob) = match options.spoken_language() {
+        LanguageMode::Fixed(code) => (code, None),
+        LanguageMode::Auto { allowed } => {
+            let (code, prob) = detect_language(state, &processed_samples, &allowed, threads)?;
+            (code.to_string(), Some(prob))
+        }
+    };
+
+    let mut params = decode_params(mode, threads, prompt);
+    params.set_language(Some(&language));
+    let mut segments = decode(ctx, state, params, &processed_samples, &speech, prompt)?;
+
+    if mode == "translate" && language != "en" {
+        let mut params = decode_params(mode, threads, prompt);
+        params.set_language(Some(&language));
+        params.set_translate(true);
+        let translated = decode(ctx, state, params, &processed_samples, &speech, prompt)?;
+        segments = attach_originals(translated, segments);
+    }
+
+    for segment in segments.iter_mut() {
+        segment.language = Some(language.clone());
+        segment.language_prob = language_prob;
+    }
+    Ok(filter.apply(segments))
+}
+
+fn decode_params<'a, 'b>(mode: &str, threads: usize, prompt: &str) -> FullParams<'a, 'b> {
     let mut params = if mode == "accuracy" {
         FullParams::new(SamplingStrategy::BeamSearch {
             beam_size: 5,
@@ -20,12 +331,10 @@ pub fn run_transcription(
     // Performance: Use configured threads
     params.set_n_threads(threads as i32);
 
-    // Language setting
-    params.set_language(Some(language));
-
-    // Quality: Provide an initial prompt to guide the model towards better punctuation and formatting.
+    // Quality: Provide an initial prompt to guide the model towards better punctuation and formatting,
+    // and towards the spelling of names and jargon from the glossary.
     // This trick is heavily used by apps like Wisprflow to get "magical" results.
-    params.set_initial_prompt("The following is a high-quality, punctuated transcript of a professional conversation. It includes proper capitalization and ignores filler words like 'um' or 'uh'.");
+    params.set_initial_prompt(prompt);
 
     // Stability: No context prevents "hallucination loops" in rolling buffers
     params.set_no_context(true);
@@ -34,54 +343,233 @@ pub fn run_transcription(
     params.set_suppress_non_speech_tokens(true);
     params.set_suppress_blank(true);
 
-    // Formality: Force single segment (often faster for short clips)
-    params.set_single_segment(true);
+    // Keep Whisper's own segmentation: the streaming transcriber stitches on segment timestamps
+    params.set_single_segment(false);
 
     params.set_print_special(false);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
-    if samples.is_empty() {
-        return Ok(String::new());
+    params
+}
+
+// One Whisper pass over the preprocessed speech clip, timed against the source audio
+fn decode(
+    ctx: &WhisperContext,
+    state: &mut WhisperState,
+    params: FullParams,
+    samples: &[f32],
+    speech: &SpeechAudio,
+    prompt: &str,
+) -> Result<Vec<TimedSegment>, String> {
+    state.full(params, samples).map_err(|e| e.to_string())?;
+
+    let num_segments = state.full_n_segments().map_err(|e| e.to_string())?;
+    let mut segments = Vec::new();
+    for i in 0..num_segments {
+        if let Ok(text) = state.full_get_segment_text(i) {
+            // Whisper timestamps are in 10 ms units, relative to the speech-only clip
+            let t0 = state.full_get_segment_t0(i).unwrap_or(0);
+            let t1 = state.full_get_segment_t1(i).unwrap_or(t0);
+            let (avg_token_prob, avg_logprob) = token_stats(ctx, state, i);
+            segments.push(TimedSegment {
+                start_secs: speech.source_secs(t0 as f32 / 100.0),
+                end_secs: speech.source_secs(t1 as f32 / 100.0),
+                text,
+                original_text: None,
+                avg_token_prob,
+                avg_logprob,
+                no_speech_prob: None,
+                language: None,
+                language_prob: None,
+            });
+        }
     }
 
-    // Silence detection
-    let rms: f32 = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
-    if rms < threshold {
-        return Ok(String::new());
+    // Robustly strip the initial prompt if Whisper hallucinates it into the output
+    for segment in segments.iter_mut() {
+        segment.text = strip_prompt_echo(&segment.text, prompt);
     }
+    segments.retain(|s| !s.text.trim().is_empty());
 
-    // Pre-process audio: DC offset removal and Peak Normalization
-    let mut processed_samples = samples.to_vec();
-    preprocess_audio(&mut processed_samples);
+    Ok(segments)
+}
+
+/// Gives each translated segment the original-language text spoken during it.
+/// The two passes segment independently, so every original segment goes to the
+/// translated one its midpoint falls in, or else the closest.
+pub(crate) fn attach_originals(
+    mut translated: Vec<TimedSegment>,
+    originals: Vec<TimedSegment>,
+) -> Vec<TimedSegment> {
+    if translated.is_empty() {
+        return translated;
+    }
 
-    let mut state = ctx.create_state().map_err(|e| e.to_string())?;
+    for original in originals {
+        let mid = (original.start_secs + original.end_secs) / 2.0;
+        let distance = |s: &TimedSegment| (s.start_secs - mid).max(mid - s.end_secs).max(0.0);
+        let target = translated
+            .iter_mut()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .unwrap();
+
+        let text = original.text.trim();
+        match &mut target.original_text {
+            Some(existing) => {
+                existing.push(' ');
+                existing.push_str(text);
+            }
+            None => target.original_text = Some(text.to_string()),
+        }
+    }
+    translated
+}
+
+// Runs Whisper's language detection over the start of `samples`
+fn detect_language(
+    state: &mut WhisperState,
+    samples: &[f32],
+    allowed: &[String],
+    threads: usize,
+) -> Result<(&'static str, f32), String> {
     state
-        .full(params, &processed_samples)
+        .pcm_to_mel(samples, threads.max(1))
+        .map_err(|e| e.to_string())?;
+    let probs = state
+        .lang_detect(0, threads.max(1))
         .map_err(|e| e.to_string())?;
 
-    let num_segments = state.full_n_segments().map_err(|e| e.to_string())?;
-    let mut result = String::new();
-    for i in 0..num_segments {
-        if let Ok(segment) = state.full_get_segment_text(i) {
-            result.push_str(&segment);
-        }
+    let candidates = probs
+        .iter()
+        .enumerate()
+        .filter_map(|(id, &p)| whisper_rs::get_lang_str(id as i32).map(|code| (code, p)));
+    pick_language(candidates, allowed)
+        .ok_or_else(|| format!("No detectable language among: {}", allowed.join(", ")))
+}
+
+// Most probable language, limited to `allowed` unless it is empty
+fn pick_language<'a>(
+    candidates: impl IntoIterator<Item = (&'a str, f32)>,
+    allowed: &[String],
+) -> Option<(&'a str, f32)> {
+    candidates
+        .into_iter()
+        .filter(|(code, _)| allowed.is_empty() || allowed.iter().any(|a| a == code))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// Mean probability and mean log probability over a segment's text tokens.
+// Special tokens (timestamps, start/end markers) all sort at or after
+// end-of-text and are left out.
+fn token_stats(ctx: &WhisperContext, state: &WhisperState, segment: i32) -> (f32, f32) {
+    let n_tokens = state.full_n_tokens(segment).unwrap_or(0);
+    let probs: Vec<f32> = (0..n_tokens)
+        .filter(|&t| {
+            state
+                .full_get_token_id(segment, t)
+                .map(|id| id < ctx.token_eot())
+                .unwrap_or(false)
+        })
+        .filter_map(|t| state.full_get_token_prob(segment, t).ok())
+        .collect();
+
+    if probs.is_empty() {
+        return (0.0, f32::NEG_INFINITY);
     }
+    let n = probs.len() as f32;
+    (
+        probs.iter().sum::<f32>() / n,
+        probs
+            .iter()
+            .map(|p| p.max(f32::MIN_POSITIVE).ln())
+            .sum::<f32>()
+            / n,
+    )
+}
 
-    let mut final_text = result.trim().to_string();
+// Fewest words in a row shared with the style sentence that count as an echo,
+// so ordinary speech that happens to share a phrase with it is kept
+const ECHO_MIN_WORDS: usize = 4;
 
-    // Robustly strip the initial prompt if Whisper hallucinates it into the output
-    let prompt_fragment = "The following is a high-quality";
-    if final_text.starts_with(prompt_fragment) {
-        if let Some(period_idx) = final_text.find("uh'.") {
-            final_text = final_text[period_idx + 4..].trim().to_string();
-        } else if let Some(period_idx) = final_text.find("uh.") {
-            final_text = final_text[period_idx + 3..].trim().to_string();
-        }
+// Where a prompt word came from, see `is_echo`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PromptPart {
+    Style,
+    Label,
+    Term(usize),
+}
+
+/// Drops a leading run of words that Whisper copied from the prompt. Matching
+/// ignores case and punctuation, and the run may come from anywhere in the prompt.
+///
+/// Glossary terms and agenda items are said aloud too, so a run inside a single
+/// term is kept; it takes the glossary label or a second term to make an echo.
+pub(crate) fn strip_prompt_echo(text: &str, prompt: &str) -> String {
+    let text = text.trim();
+    let (style, terms) = crate::glossary::split_prompt(prompt);
+    let mut prompt_words: Vec<(String, PromptPart)> = words(style)
+        .map(|(word, _)| (word, PromptPart::Style))
+        .collect();
+    if !terms.is_empty() {
+        prompt_words.push(("glossary".to_string(), PromptPart::Label));
+    }
+    for (index, term) in terms.iter().enumerate() {
+        prompt_words.extend(words(term).map(|(word, _)| (word, PromptPart::Term(index))));
     }
+    let text_words: Vec<(String, usize)> = words(text).collect();
 
-    Ok(final_text)
+    let echoed = (0..prompt_words.len())
+        .map(|start| {
+            let len = prompt_words[start..]
+                .iter()
+                .zip(&text_words)
+                .take_while(|((p, _), (t, _))| p == t)
+                .count();
+            &prompt_words[start..start + len]
+        })
+        .filter(|run| is_echo(run))
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+
+    if echoed == 0 {
+        return text.to_string();
+    }
+    let end = text_words[echoed - 1].1;
+    text[end..]
+        .trim_start_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+        .to_string()
+}
+
+// A run of the style sentence needs a few words to be more than coincidence;
+// a run of the glossary needs to span the label or more than one term
+fn is_echo(run: &[(String, PromptPart)]) -> bool {
+    let Some((_, first)) = run.first() else {
+        return false;
+    };
+    if run.len() >= 2 && run.iter().any(|(_, part)| *part == PromptPart::Label) {
+        return true;
+    }
+    match first {
+        PromptPart::Style => run.len() >= ECHO_MIN_WORDS,
+        PromptPart::Label => false,
+        PromptPart::Term(_) => run.iter().any(|(_, part)| part != first),
+    }
+}
+
+// Words normalised for comparison, each with the byte offset where it ends
+fn words(text: &str) -> impl Iterator<Item = (String, usize)> + '_ {
+    text.split_whitespace().filter_map(move |word| {
+        let normalised: String = word
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        let end = word.as_ptr() as usize - text.as_ptr() as usize + word.len();
+        (!normalised.is_empty()).then_some((normalised, end))
+    })
 }
 
 pub fn preprocess_audio(samples: &mut [f32]) {
@@ -108,3 +596,171 @@ pub fn preprocess_audio(samples: &mut [f32]) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_mode_from_config() {
+        assert_eq!(
+            LanguageMode::from_config("pl", &[]),
+            LanguageMode::Fixed("pl".to_string())
+        );
+        assert_eq!(
+            LanguageMode::from_config("auto", &[" EN".to_string(), "".to_string()]),
+            LanguageMode::Auto {
+                allowed: vec!["en".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_translate_mode_detects_instead_of_default_english() {
+        let mut options = DecodeOptions {
+            threshold: 0.005,
+            mode: "translate".to_string(),
+            language: LanguageMode::from_config("en", &[]),
+            threads: 4,
+            prompt: String::new(),
+            filter: HallucinationFilter::new(&[]),
+        };
+        assert_eq!(
+            options.spoken_language(),
+            LanguageMode::Auto { allowed: vec![] }
+        );
+
+        options.language = LanguageMode::Fixed("pl".to_string());
+        assert_eq!(options.spoken_language(), options.language);
+        options.mode = "speed".to_string();
+        options.language = LanguageMode::Fixed("en".to_string());
+        assert_eq!(options.spoken_language(), options.language);
+    }
+
+    #[test]
+    fn test_pick_language_respects_allow_list() {
+        let probs = [("en", 0.2), ("ru", 0.5), ("pl", 0.3)];
+        assert_eq!(pick_language(probs, &[]), Some(("ru", 0.5)));
+
+        let allowed = ["en".to_string(), "pl".to_string()];
+        assert_eq!(pick_language(probs, &allowed), Some(("pl", 0.3)));
+
+        assert_eq!(pick_language(probs, &["de".to_string()]), None);
+    }
+
+    #[test]
+    fn test_attach_originals_by_time() {
+        let translated = vec![
+            TimedSegment::new(0.0, 3.0, "Hello everyone."),
+            TimedSegment::new(3.0, 6.0, "Let's start with the budget."),
+        ];
+        let originals = vec![
+            TimedSegment::new(0.0, 1.5, "Bonjour"),
+            TimedSegment::new(1.5, 2.8, "à tous."),
+            TimedSegment::new(2.8, 6.2, "Commençons par le budget."),
+        ];
+
+        let segments = attach_originals(translated, originals);
+        assert_eq!(
+            segments[0].original_text.as_deref(),
+            Some("Bonjour à tous.")
+        );
+        assert_eq!(
+            segments[1].original_text.as_deref(),
+            Some("Commençons par le budget.")
+        );
+        assert_eq!(segments[1].text, "Let's start with the budget.");
+    }
+
+    #[test]
+    fn test_attach_originals_outside_any_span_go_to_nearest() {
+        let translated = vec![
+            TimedSegment::new(1.0, 2.0, "Yes."),
+            TimedSegment::new(5.0, 6.0, "No."),
+        ];
+        let originals = vec![
+            TimedSegment::new(2.5, 3.0, "Oui."),
+            TimedSegment::new(6.5, 7.5, "Non."),
+        ];
+
+        let segments = attach_originals(translated, originals);
+        assert_eq!(segments[0].original_text.as_deref(), Some("Oui."));
+        assert_eq!(segments[1].original_text.as_deref(), Some("Non."));
+        assert!(attach_originals(Vec::new(), vec![TimedSegment::new(0.0, 1.0, "Oui.")]).is_empty());
+    }
+
+    const PROMPT: &str = "The following is a high-quality, punctuated transcript of a professional conversation. Glossary: Kuroko, BlackHole, OKR.";
+
+    #[test]
+    fn test_strip_prompt_echo_removes_copied_prompt() {
+        assert_eq!(
+            strip_prompt_echo(
+                " The following is a high-quality, punctuated transcript. So, where were we?",
+                PROMPT
+            ),
+            "So, where were we?"
+        );
+        // From the middle of the prompt, with different casing and punctuation
+        assert_eq!(
+            strip_prompt_echo("glossary kuroko, blackhole OKR. Let's begin.", PROMPT),
+            "Let's begin."
+        );
+        assert_eq!(
+            strip_prompt_echo("Glossary: Kuroko, BlackHole, OKR.", PROMPT),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_strip_prompt_echo_keeps_real_speech() {
+        assert_eq!(
+            strip_prompt_echo("Kuroko is listening.", PROMPT),
+            "Kuroko is listening."
+        );
+        assert_eq!(
+            strip_prompt_echo("The following is the plan.", PROMPT),
+            "The following is the plan."
+        );
+        assert_eq!(strip_prompt_echo(" OKR. ", PROMPT), "OKR.");
+        assert_eq!(
+            strip_prompt_echo("Glossary of terms is ready.", PROMPT),
+            "Glossary of terms is ready."
+        );
+        assert_eq!(strip_prompt_echo("Hello there.", ""), "Hello there.");
+    }
+
+    #[test]
+    fn test_strip_prompt_echo_removes_agenda_echo() {
+        let prompt = crate::glossary::build_prompt([
+            "Kuroko",
+            "Agree on the Q3 budget",
+            "Hire two engineers",
+        ]);
+        assert_eq!(
+            strip_prompt_echo("Glossary: Kuroko, Agree on the Q3 budget.", &prompt),
+            ""
+        );
+        assert_eq!(
+            strip_prompt_echo(
+                "Agree on the Q3 budget, hire two engineers. So, where were we?",
+                &prompt
+            ),
+            "So, where were we?"
+        );
+        // One item said in a sentence is speech, and is what scoring should see
+        assert_eq!(
+            strip_prompt_echo("Agree on the Q3 budget today.", &prompt),
+            "Agree on the Q3 budget today."
+        );
+        assert_eq!(
+            strip_prompt_echo("Let's agree on the Q3 budget.", &prompt),
+            "Let's agree on the Q3 budget."
+        );
+    }
+
+    #[test]
+    fn test_load_model_rejects_missing_file() {
+        let err = load_model("/nonexistent/ggml-small.bin").err().unwrap();
+        assert!(err.contains("not found"), "{}", err);
+    }
+}
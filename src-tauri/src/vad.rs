@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const SAMPLE_RATE: usize = 16000;
+/// 30 ms analysis frames at 16 kHz.
+pub const FRAME_SAMPLES: usize = SAMPLE_RATE * 30 / 1000;
+
+// A frame must be this many times louder than the noise floor
+const NOISE_MARGIN: f32 = 2.0;
+// Fraction of sign changes per sample above which a frame reads as hiss, not voice
+const MAX_SPEECH_ZCR: f32 = 0.35;
+// Offline: the noise floor is this quantile of all frame levels
+const NOISE_FLOOR_QUANTILE: f32 = 0.1;
+// Streaming: the noise floor is the quietest frame of the last 3 s, once
+// there is enough history to trust it
+const NOISE_WINDOW_FRAMES: usize = 100;
+const MIN_NOISE_FRAMES: usize = 10;
+
+// Streaming: frames of voice needed to start speech, frames of quiet to end it
+const ONSET_FRAMES: usize = 3;
+const HANGOVER_FRAMES: usize = 10;
+
+// Offline: context kept around each span, and gaps short enough to bridge
+const SPAN_PADDING: usize = SAMPLE_RATE / 4;
+const MERGE_GAP: usize = SAMPLE_RATE / 2;
+// Whisper misbehaves on clips shorter than a second
+const MIN_WHISPER_SAMPLES: usize = SAMPLE_RATE;
+
+/// A run of speech in a 16 kHz buffer, as sample indices `[start, end)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeechSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+// RMS and zero-crossing rate of one frame
+fn frame_stats(frame: &[f32]) -> (f32, f32) {
+    if frame.is_empty() {
+        return (0.0, 0.0);
+    }
+    let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+    let crossings = frame
+        .windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    (rms, crossings as f32 / frame.len() as f32)
+}
+
+/// A frame is voiced when its RMS clears both `threshold` and the noise floor
+/// by `NOISE_MARGIN`, and its zero-crossing rate is low enough to rule out
+/// broadband noise.
+fn is_voiced(rms: f32, zcr: f32, threshold: f32, noise_floor: Option<f32>) -> bool {
+    rms >= threshold && rms >= noise_floor.unwrap_or(0.0) * NOISE_MARGIN && zcr <= MAX_SPEECH_ZCR
+}
+
+/// Marks the speech regions of a 16 kHz buffer.
+///
+/// Voiced frames are grouped into spans, padded with a little context on
+/// both sides, and spans separated by short pauses are merged.
+pub fn detect_speech(samples: &[f32], threshold: f32) -> Vec<SpeechSpan> {
+    let frames: Vec<(f32, f32)> = samples.chunks(FRAME_SAMPLES).map(frame_stats).collect();
+
+    let mut levels: Vec<f32> = frames.iter().map(|&(rms, _)| rms).collect();
+    levels.sort_by(|a, b| a.total_cmp(b));
+    let noise_floor = levels
+        .get((levels.len() as f32 * NOISE_FLOOR_QUANTILE) as usize)
+        .copied();
+
+    let mut spans: Vec<SpeechSpan> = Vec::new();
+    for (i, &(rms, zcr)) in frames.iter().enumerate() {
+        if !is_voiced(rms, zcr, threshold, noise_floor) {
+            continue;
+        }
+
+        let start = (i * FRAME_SAMPLES).saturating_sub(SPAN_PADDING);
+        let end = ((i + 1) * FRAME_SAMPLES + SPAN_PADDING).min(samples.len());
+        match spans.last_mut() {
+            Some(last) if start <= last.end + MERGE_GAP => last.end = end,
+            _ => spans.push(SpeechSpan { start, end }),
+        }
+    }
+
+    spans
+}
+
+/// The speech spans of a buffer joined into one clip for Whisper.
+pub struct SpeechAudio {
+    pub samples: Vec<f32>,
+    // (offset in `samples`, offset in the source buffer, length)
+    pieces: Vec<(usize, usize, usize)>,
+}
+
+impl SpeechAudio {
+    pub fn from_spans(source: &[f32], spans: &[SpeechSpan]) -> Self {
+        let mut samples = Vec::new();
+        let mut pieces = Vec::with_capacity(spans.len());
+        for span in spans {
+            let end = span.end.min(source.len());
+            if span.start >= end {
+                continue;
+            }
+            pieces.push((samples.len(), span.start, end - span.start));
+            samples.extend_from_slice(&source[span.start..end]);
+        }
+
+        if !samples.is_empty() && samples.len() < MIN_WHISPER_SAMPLES {
+            samples.resize(MIN_WHISPER_SAMPLES, 0.0);
+        }
+
+        SpeechAudio { samples, pieces }
+    }
+
+    /// Maps a time in the joined clip back to the source buffer.
+    pub fn source_secs(&self, secs: f32) -> f32 {
+        let pos = (secs.max(0.0) * SAMPLE_RATE as f32) as usize;
+        let piece = self
+            .pieces
+            .iter()
+            .rev()
+            .find(|(offset, _, _)| *offset <= pos)
+            .or(self.pieces.first());
+
+        match piece {
+            Some(&(offset, source_start, len)) => {
+                // Time in the trailing padding sticks to the end of the last piece
+                let within = pos.saturating_sub(offset).min(len);
+                (source_start + within) as f32 / SAMPLE_RATE as f32
+            }
+            None => secs,
+        }
+    }
+}
+
+/// Live speech state of a capture channel, updated from the capture path.
+pub struct VoiceActivity {
+    threshold: f32,
+    speech_frames: AtomicU64,
+}
+
+impl VoiceActivity {
+    pub fn new(threshold: f32) -> Self {
+        VoiceActivity {
+            threshold,
+            speech_frames: AtomicU64::new(0),
+        }
+    }
+
+    /// Total frames judged to be speech so far. When it hasn't moved since
+    /// the last transcription, nothing new was said.
+    pub fn speech_frames(&self) -> u64 {
+        self.speech_frames.load(Ordering::Relaxed)
+    }
+}
+
+/// Frame-by-frame VAD over a live stream, owned by the capture writer.
+///
+/// Speech starts after `ONSET_FRAMES` voiced frames in a row and continues
+/// through up to `HANGOVER_FRAMES` quiet ones, so short pauses and unvoiced
+/// consonants stay inside an utterance.
+#[derive(Default)]
+pub struct VadTracker {
+    recent_levels: VecDeque<f32>,
+    pending: Vec<f32>,
+    voiced_run: usize,
+    quiet_run: usize,
+    speaking: bool,
+}
+
+impl VadTracker {
+    pub fn push(&mut self, samples: &[f32], activity: &VoiceActivity) {
+        self.pending.extend_from_slice(samples);
+
+        let mut consumed = 0;
+        for frame in self.pending.chunks_exact(FRAME_SAMPLES) {
+            consumed += FRAME_SAMPLES;
+
+            let (rms, zcr) = frame_stats(frame);
+            let noise_floor = if self.recent_levels.len() >= MIN_NOISE_FRAMES {
+                self.recent_levels.iter().copied().reduce(f32::min)
+            } else {
+                None
+            };
+            if self.recent_levels.len() == NOISE_WINDOW_FRAMES {
+                self.recent_levels.pop_front();
+            }
+            self.recent_levels.push_back(rms);
+
+            if is_voiced(rms, zcr, activity.threshold, noise_floor) {
+                self.voiced_run += 1;
+                self.quiet_run = 0;
+            } else {
+                self.voiced_run = 0;
+                self.quiet_run += 1;
+            }
+
+            if self.voiced_run >= ONSET_FRAMES {
+                self.speaking = true;
+            } else if self.quiet_run > HANGOVER_FRAMES {
+                self.speaking = false;
+            }
+
+            if self.speaking {
+                activity.speech_frames.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.pending.drain(..consumed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(secs: f32, amplitude: f32) -> Vec<f32> {
+        let len = (secs * SAMPLE_RATE as f32) as usize;
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    fn silence(secs: f32) -> Vec<f32> {
+        vec![0.0; (secs * SAMPLE_RATE as f32) as usize]
+    }
+
+    // Deterministic broadband noise
+    fn hiss(secs: f32, amplitude: f32) -> Vec<f32> {
+        let mut state = 0x2545_f491u32;
+        (0..(secs * SAMPLE_RATE as f32) as usize)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                amplitude * (state as f32 / u32::MAX as f32 * 2.0 - 1.0)
+            })
+            .collect()
+    }
+
+    fn secs(samples: usize) -> f32 {
+        samples as f32 / SAMPLE_RATE as f32
+    }
+
+    #[test]
+    fn test_detect_speech_finds_quiet_speaker_after_loud_one() {
+        let mut samples = tone(1.0, 0.5);
+        samples.extend(silence(3.0));
+        samples.extend(tone(1.0, 0.02));
+        samples.extend(silence(2.0));
+
+        let spans = detect_speech(&samples, 0.005);
+        assert_eq!(spans.len(), 2);
+        assert!(secs(spans[0].start) < 0.05 && (secs(spans[0].end) - 1.25).abs() < 0.05);
+        assert!((secs(spans[1].start) - 3.75).abs() < 0.05);
+        assert!((secs(spans[1].end) - 5.25).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_detect_speech_merges_short_pauses() {
+        let mut samples = tone(0.5, 0.3);
+        samples.extend(silence(0.6));
+        samples.extend(tone(0.5, 0.3));
+
+        let spans = detect_speech(&samples, 0.005);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].end, samples.len());
+    }
+
+    #[test]
+    fn test_detect_speech_ignores_silence_and_hiss() {
+        assert!(detect_speech(&silence(2.0), 0.005).is_empty());
+        assert!(detect_speech(&hiss(2.0, 0.3), 0.005).is_empty());
+        assert!(detect_speech(&tone(2.0, 0.003), 0.005).is_empty());
+    }
+
+    #[test]
+    fn test_detect_speech_adapts_to_steady_noise() {
+        // A loud hum the whole time, with speech well above it for a second
+        let mut samples = tone(4.0, 0.05);
+        for (s, v) in samples[32000..48000].iter_mut().zip(tone(1.0, 0.4)) {
+            *s += v;
+        }
+
+        let spans = detect_speech(&samples, 0.005);
+        assert_eq!(spans.len(), 1);
+        assert!((secs(spans[0].start) - 1.75).abs() < 0.05);
+        assert!((secs(spans[0].end) - 3.25).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_speech_audio_joins_spans_and_maps_times() {
+        let source: Vec<f32> = (0..SAMPLE_RATE * 10).map(|i| i as f32).collect();
+        let spans = [
+            SpeechSpan {
+                start: 16000,
+                end: 32000,
+            },
+            SpeechSpan {
+                start: 80000,
+                end: 112000,
+            },
+        ];
+        let audio = SpeechAudio::from_spans(&source, &spans);
+        assert_eq!(audio.samples.len(), 48000);
+        assert_eq!(audio.samples[0], 16000.0);
+        assert_eq!(audio.samples[16000], 80000.0);
+
+        assert!((audio.source_secs(0.5) - 1.5).abs() < 1e-3);
+        assert!((audio.source_secs(1.0) - 5.0).abs() < 1e-3);
+        assert!((audio.source_secs(2.5) - 6.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_speech_audio_pads_short_clips() {
+        let source = tone(2.0, 0.5);
+        let audio = SpeechAudio::from_spans(
+            &source,
+            &[SpeechSpan {
+                start: 8000,
+                end: 12000,
+            }],
+        );
+        assert_eq!(audio.samples.len(), MIN_WHISPER_SAMPLES);
+        // Time inside the padding stays at the end of the speech
+        assert!((audio.source_secs(0.9) - 0.75).abs() < 1e-3);
+
+        assert!(SpeechAudio::from_spans(&source, &[]).samples.is_empty());
+    }
+
+    #[test]
+    fn test_tracker_counts_speech_with_onset_and_hangover() {
+        let activity = VoiceActivity::new(0.005);
+        let mut tracker = VadTracker::default();
+
+        // Whole frames of silence, so speech starts on a frame boundary
+        tracker.push(&silence(0.99), &activity);
+        assert_eq!(activity.speech_frames(), 0);
+
+        // Odd block sizes, as they come out of the resampler
+        let speech = tone(0.6, 0.3);
+        for block in speech.chunks(147) {
+            tracker.push(block, &activity);
+        }
+        let voiced_frames = speech.len() / FRAME_SAMPLES;
+        let after_speech = activity.speech_frames();
+        assert_eq!(after_speech, (voiced_frames - ONSET_FRAMES + 1) as u64);
+
+        // The hangover carries on briefly, then counting stops
+        tracker.push(&silence(1.0), &activity);
+        let after_pause = activity.speech_frames();
+        assert_eq!(after_pause - after_speech, HANGOVER_FRAMES as u64);
+        tracker.push(&silence(1.0), &activity);
+        assert_eq!(activity.speech_frames(), after_pause);
+    }
+}
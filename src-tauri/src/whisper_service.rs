@@ -0,0 +1,88 @@
+use crate::transcription::{run_transcription, DecodeOptions, TimedSegment, Transcriber};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc;
+use whisper_rs::WhisperContext;
+
+struct Job {
+    samples: Vec<f32>,
+    options: DecodeOptions,
+    reply: mpsc::Sender<Result<Vec<TimedSegment>, String>>,
+}
+
+/// Runs whisper-rs on a thread of its own.
+///
+/// The thread owns the model and a single decoding state, allocated once and
+/// reused for every pass. Requests queue up and decode one at a time, so the
+/// background worker and a hotkey press never run Whisper side by side. The
+/// thread exits once the service is dropped and its last request is answered.
+/// A decode that panics fails only its own request; the state is then rebuilt
+/// for the next one.
+pub struct WhisperService {
+    requests: mpsc::Sender<Job>,
+}
+
+impl WhisperService {
+    pub fn spawn(ctx: WhisperContext) -> Result<Self, String> {
+        let (requests, queue) = mpsc::channel::<Job>();
+        let (ready_tx, ready) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut state = match ctx.create_state() {
+                Ok(state) => state,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(format!("Failed to create Whisper state: {}", e)));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+
+            for job in queue {
+                let result = catch_unwind(AssertUnwindSafe(|| {
+                    run_transcription(&ctx, &mut state, &job.samples, &job.options)
+                }));
+                let result = match result {
+                    Ok(result) => result,
+                    Err(_) => {
+                        eprintln!("[Whisper] Decode panicked, starting a fresh state");
+                        match ctx.create_state() {
+                            Ok(fresh) => state = fresh,
+                            Err(e) => {
+                                let _ = job
+                                    .reply
+                                    .send(Err(format!("Failed to create Whisper state: {}", e)));
+                                return;
+                            }
+                        }
+                        Err("Whisper failed while decoding".to_string())
+                    }
+                };
+                let _ = job.reply.send(result);
+            }
+        });
+
+        ready
+            .recv()
+            .map_err(|_| "Whisper service failed to start".to_string())??;
+        Ok(WhisperService { requests })
+    }
+}
+
+impl Transcriber for WhisperService {
+    fn transcribe(
+        &self,
+        samples: &[f32],
+        options: &DecodeOptions,
+    ) -> Result<Vec<TimedSegment>, String> {
+        let (reply, result) = mpsc::channel();
+        self.requests
+            .send(Job {
+                samples: samples.to_vec(),
+                options: options.clone(),
+                reply,
+            })
+            .map_err(|_| "Whisper service has stopped".to_string())?;
+        result
+            .recv()
+            .map_err(|_| "Whisper service has stopped".to_string())?
+    }
+}
@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { parseGeminiStreamChunk, extractStructuredData, StructuredResponse } from "./utils/gemini";
+import { Transcript } from "./utils/transcript";
 import { StealthView } from "./components/StealthView";
 import { NormalView } from "./components/NormalView";
 import { SettingsView, AppConfig } from "./components/SettingsView";
@@ -126,7 +127,8 @@ function App() {
     try {
       console.log("Process triggered");
       // 1. Get Transcription
-      const text = await invoke<string>("transcribe_latest");
+      const latest = await invoke<Transcript>("transcribe_latest");
+      const text = latest.text;
       const transcriptionTime = performance.now();
       console.log(`[Latency] Transcription took: ${(transcriptionTime - startTime).toFixed(0)}ms`);
       console.log("Transcript:", text);
@@ -167,6 +169,10 @@ function App() {
         prompt += `Supplemental Context:\n${supplementalContext}\n\n`;
       }
 
+      if (/^(You|Remote):/m.test(text)) {
+        prompt += `Lines starting with "You:" were spoken by the user; lines starting with "Remote:" by other participants.\n\n`;
+      }
+
       prompt += `Transcript snippet:\n${text}`;
 
       const geminiResponse = await fetch(geminiUrl, {
@@ -225,7 +231,7 @@ function App() {
         logText = `Q: ${finalStructured.cleaned_question}\nA: ${finalStructured.answer}\nConfidence: ${finalStructured.confidence.toFixed(2)}`;
       }
 
-      invoke("log_session", { transcript: text, answer: logText }).catch(console.error);
+      invoke("log_session", { transcript: latest, answer: logText }).catch(console.error);
 
     } catch (err: any) {
       console.error(err);
@@ -266,10 +272,24 @@ function App() {
     };
   }, []);
 
+  // Whisper Model Reload Listener
+  useEffect(() => {
+    const unlistenModel = listen<{ state: string; path: string; message?: string }>("whisper-model-status", (event) => {
+      if (event.payload.state === "failed") {
+        setError(event.payload.message || `Failed to load Whisper model: ${event.payload.path}`);
+      } else if (event.payload.state === "loaded") {
+        setError("");
+      }
+    });
+    return () => {
+      unlistenModel.then(f => f());
+    };
+  }, []);
+
   // Live Transcription Listener
   useEffect(() => {
-    const unlistenLive = listen<string>("live-transcript", (event) => {
-      setTranscript(event.payload);
+    const unlistenLive = listen<Transcript>("live-transcript", (event) => {
+      setTranscript(event.payload.text);
     });
     return () => {
       unlistenLive.then(f => f());
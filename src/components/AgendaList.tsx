@@ -1,26 +1,38 @@
+export type AgendaStatus = "pending" | "in_progress" | "answered" | "skipped" | "deferred" | "reopened";
+
+export interface StatusChange {
+    from: AgendaStatus;
+    to: AgendaStatus;
+    at_ms: number;
+}
+
 export interface AgendaItem {
     id: string;
     text: string;
-    status: "pending" | "captured" | "answered";
+    status: AgendaStatus;
     score: number; // 0.0 - 1.0
     evidence: string[];
     answer?: string;
+    history?: StatusChange[];
 }
 
 interface AgendaListProps {
     items: AgendaItem[];
     status: string;
     onExpandItem: (id: string, text: string) => void;
+    onSetStatus: (id: string, status: AgendaStatus) => void;
 }
 
-export function AgendaList({ items, status, onExpandItem }: AgendaListProps) {
+const isSettled = (status: AgendaStatus) => status === 'answered' || status === 'skipped' || status === 'deferred';
+
+export function AgendaList({ items, status, onExpandItem, onSetStatus }: AgendaListProps) {
     const answeredCount = items.filter(i => i.status === 'answered').length;
 
     return (
         <div className="flex-1 flex flex-col min-h-0 min-w-0">
             <label className="text-[10px] text-white/40 uppercase font-bold tracking-wider flex items-center justify-between mb-2">
                 <span>Tracked Agenda ({answeredCount}/{items.length})</span>
-                {items.some(i => i.status !== 'answered') && (
+                {items.some(i => !isSettled(i.status)) && (
                     <span className="flex items-center gap-1 text-white/20">
                         <div className="w-1.5 h-1.5 rounded-full bg-green-500/50 animate-pulse"></div>
                         Tracking
@@ -34,15 +46,17 @@ export function AgendaList({ items, status, onExpandItem }: AgendaListProps) {
                         key={item.id}
                         className={`p-3 rounded-lg border text-sm transition-all shrink-0 flex flex-col gap-2 ${item.status === 'answered'
                             ? "bg-green-500/10 border-green-500/20"
-                            : item.status === 'captured'
+                            : item.status === 'in_progress'
                                 ? "bg-blue-500/10 border-blue-500/20"
-                                : "bg-white/5 border-white/5"
+                                : item.status === 'skipped' || item.status === 'deferred'
+                                    ? "bg-white/5 border-white/5 opacity-50"
+                                    : "bg-white/5 border-white/5"
                             }`}
                     >
                         <div className="flex items-start gap-2">
                             <div className={`mt-0.5 min-w-[20px] h-5 flex items-center justify-center rounded-full text-[10px] font-bold ${item.status === 'answered'
                                     ? "bg-green-500 text-black"
-                                    : item.status === 'captured'
+                                    : item.status === 'in_progress'
                                         ? "bg-blue-500 text-white"
                                         : "bg-white/10 text-white/50"
                                 }`}>
@@ -63,10 +77,38 @@ export function AgendaList({ items, status, onExpandItem }: AgendaListProps) {
                                             <svg xmlns="http://www.w3.org/2000/svg" width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round"><path d="m21.64 3.64-1.28-1.28a1.21 1.21 0 0 0-1.72 0L2.36 18.64a1.21 1.21 0 0 0 0 1.72l1.28 1.28a1.2 1.2 0 0 0 1.72 0L21.64 5.36a1.2 1.2 0 0 0 0-1.72Z"/><path d="m14 7 3 3"/><path d="M5 6v4"/><path d="M19 14v4"/><path d="M10 2v2"/><path d="M7 8H3"/><path d="M21 16h-4"/><path d="M11 3H9"/></svg>
                                         </button>
                                     )}
+                                    <div className="flex items-center gap-1 shrink-0">
+                                        {isSettled(item.status) ? (
+                                            <button
+                                                onClick={() => onSetStatus(item.id, "reopened")}
+                                                className="px-1.5 py-0.5 hover:bg-white/10 rounded text-[10px] text-white/40 hover:text-white transition-colors"
+                                                title="Track this item again"
+                                            >
+                                                Reopen
+                                            </button>
+                                        ) : (
+                                            <>
+                                                <button
+                                                    onClick={() => onSetStatus(item.id, "deferred")}
+                                                    className="px-1.5 py-0.5 hover:bg-white/10 rounded text-[10px] text-white/40 hover:text-white transition-colors"
+                                                    title="Come back to this later"
+                                                >
+                                                    Defer
+                                                </button>
+                                                <button
+                                                    onClick={() => onSetStatus(item.id, "skipped")}
+                                                    className="px-1.5 py-0.5 hover:bg-white/10 rounded text-[10px] text-white/40 hover:text-white transition-colors"
+                                                    title="Stop tracking this item"
+                                                >
+                                                    Skip
+                                                </button>
+                                            </>
+                                        )}
+                                    </div>
                                 </div>
 
                                 {/* Progress Bar */}
-                                {item.score > 0 && item.status !== 'answered' && (
+                                {item.score > 0 && !isSettled(item.status) && (
                                     <div className="mt-2 w-full h-1 bg-white/10 rounded-full overflow-hidden">
                                         <div
                                             className="h-full bg-blue-500 transition-all duration-500"
@@ -4,7 +4,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { listen } from "@tauri-apps/api/event";
 import { useEffect, useState } from "react";
-import { AgendaList, AgendaItem } from "./AgendaList";
+import { AgendaList, AgendaItem, AgendaStatus } from "./AgendaList";
 import { TranscriptionDisplay } from "./TranscriptionDisplay";
 import { InsightView } from "./InsightView";
 import { BufferVisualizer } from "./BufferVisualizer";
@@ -49,6 +49,7 @@ export function NormalView({
     const [agendaItems, setAgendaItems] = useState<AgendaItem[]>([]);
     const [agendaStatus, setAgendaStatus] = useState<string>("");
     const [audioDevice, setAudioDevice] = useState<string>("");
+    const [deviceState, setDeviceState] = useState<string>("connected");
     const [isContextExpanded, setIsContextExpanded] = useState(true);
 
     // 0. Fetch Audio Device
@@ -68,9 +69,18 @@ export function NormalView({
             setTimeout(() => setAgendaStatus(""), timeout);
         });
 
+        const unlistenDevice = listen<{ role: string; state: string; device?: string }>("audio-device-status", (event) => {
+            if (event.payload.role !== "loopback") return;
+            setDeviceState(event.payload.state);
+            if (event.payload.state === "connected" && event.payload.device) {
+                setAudioDevice(event.payload.device);
+            }
+        });
+
         return () => {
             unlistenPromise.then(f => f());
             unlistenStatus.then(f => f());
+            unlistenDevice.then(f => f());
         };
     }, []);
 
@@ -151,6 +161,16 @@ export function NormalView({
         }
     };
 
+    const handleSetStatus = async (id: string, status: AgendaStatus) => {
+        try {
+            const items = await invoke<AgendaItem[]>("set_agenda_status", { id, status });
+            setAgendaItems(items);
+        } catch (e: any) {
+            console.error("Failed to update agenda item:", e);
+            setAgendaStatus(`${e}`);
+        }
+    };
+
     const startDrag = async (e: React.MouseEvent) => {
         // Only drag if not clicking on interactive elements
         const target = e.target as HTMLElement;
@@ -223,8 +243,8 @@ export function NormalView({
                             {audioDevice && (
                                 <>
                                     <span className="text-white/10 mx-1">•</span>
-                                    <span className="text-[10px] uppercase tracking-wider font-mono text-white/30 truncate max-w-[150px]" title={audioDevice}>
-                                        {audioDevice}
+                                    <span className={`text-[10px] uppercase tracking-wider font-mono truncate max-w-[150px] ${deviceState === "connected" ? "text-white/30" : "text-amber-400/70"}`} title={audioDevice}>
+                                        {deviceState === "connected" ? audioDevice : `${deviceState}...`}
                                     </span>
                                 </>
                             )}
@@ -323,7 +343,7 @@ export function NormalView({
                         />
                     </div>
 
-                    <AgendaList items={agendaItems} status={agendaStatus} onExpandItem={handleExpandItem} />
+                    <AgendaList items={agendaItems} status={agendaStatus} onExpandItem={handleExpandItem} onSetStatus={handleSetStatus} />
                 </div>
 
                 {/* Right: Transcript & AI Insights */}
@@ -13,19 +13,36 @@ export interface AppConfig {
   ollama_embedding_model?: string;
   ollama_min_chars: number;
   min_confidence: number;
-  transcription_mode: "speed" | "accuracy";
+  transcription_mode: "speed" | "accuracy" | "translate";
   whisper_language: string;
+  whisper_language_allowlist: string[];
+  whisper_glossary: string[];
+  whisper_blocklist: string[];
   silence_threshold: number;
   agenda_similarity_threshold: number;
   transcription_interval_secs: number;
   agenda_check_cooldown_secs: number;
+  agenda_batch_size: number;
   cache_freshness_secs: number;
   ollama_base_url: string;
   whisper_threads: number;
   min_analysis_chars: number;
+  mic_device?: string;
+  capture_mix_mode: "mixed" | "separate";
+  audio_source_file?: string;
+  replay_speed: number;
+  transcription_backend: "local" | "http";
+  transcription_url: string;
+  transcription_api_key?: string;
+  transcription_model?: string;
   error?: string;
 }
 
+export interface AudioDeviceInfo {
+  name: string;
+  role?: "loopback" | "mic" | null;
+}
+
 interface SettingsViewProps {
   config: AppConfig;
   defaultMode: "validate" | "answer" | "followup";
@@ -58,6 +75,9 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
 
   const [isSaving, setIsSaving] = useState(false);
   const [lastSavedConfig, setLastSavedConfig] = useState<AppConfig>(config);
+  const [allowlistText, setAllowlistText] = useState((config.whisper_language_allowlist || []).join(", "));
+  const [glossaryText, setGlossaryText] = useState((config.whisper_glossary || []).join("\n"));
+  const [blocklistText, setBlocklistText] = useState((config.whisper_blocklist || []).join("\n"));
 
   // Validation States
   const [geminiValidation, setGeminiValidation] = useState<"idle" | "validating" | "valid" | "invalid">("idle");
@@ -67,8 +87,9 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
   const [ollamaStatus, setOllamaStatus] = useState<"checking" | "present" | "absent">("checking");
   const [ollamaModels, setOllamaModels] = useState<string[]>([]);
 
-  const [audioDevices, setAudioDevices] = useState<string[]>([]);
+  const [audioDevices, setAudioDevices] = useState<AudioDeviceInfo[]>([]);
   const [selectedDevice, setSelectedDevice] = useState<string>("");
+  const [selectedMic, setSelectedMic] = useState<string>("");
 
   // Initial Checks
   useEffect(() => {
@@ -86,8 +107,9 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
     invoke<boolean>("validate_hotkey", { hotkey: config.global_hotkey }).then(valid => setHotkeyValidation(valid ? "valid" : "invalid"));
 
     // Fetch audio devices
-    invoke<string[]>("list_audio_devices").then(setAudioDevices).catch(console.error);
-    invoke<string>("get_audio_device").then(setSelectedDevice).catch(console.error);
+    invoke<AudioDeviceInfo[]>("list_audio_devices").then(setAudioDevices).catch(console.error);
+    invoke<string>("get_audio_device", { role: "loopback" }).then(setSelectedDevice).catch(console.error);
+    invoke<string>("get_audio_device", { role: "mic" }).then(setSelectedMic).catch(console.error);
   }, []);
 
   // Auto-Save Effect
@@ -98,8 +120,9 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
 
       setIsSaving(true);
 
-      // Validate GGML Path
-      const isPathValid = await invoke<boolean>("validate_file_path", { path: debouncedFormData.whisper_ggml_path });
+      // Validate GGML Path (a transcription server needs no local model)
+      const isPathValid = debouncedFormData.transcription_backend === "http"
+        || await invoke<boolean>("validate_file_path", { path: debouncedFormData.whisper_ggml_path });
       setPathValidation(isPathValid ? "valid" : "invalid");
 
       // Validate Hotkey
@@ -159,13 +182,23 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
 
   const handleDeviceChange = async (deviceName: string) => {
     try {
-      await invoke("set_audio_device", { name: deviceName });
+      await invoke("set_audio_device", { name: deviceName, role: "loopback" });
       setSelectedDevice(deviceName);
     } catch (e) {
       console.error("Failed to set audio device", e);
     }
   };
 
+  const handleMicChange = async (deviceName: string) => {
+    try {
+      await invoke("set_audio_device", { name: deviceName, role: "mic" });
+      setSelectedMic(deviceName);
+      handleChange("mic_device", deviceName);
+    } catch (e) {
+      console.error("Failed to set microphone", e);
+    }
+  };
+
   const startDrag = async (e: React.MouseEvent) => {
     // Only drag if not clicking on interactive elements
     const target = e.target as HTMLElement;
@@ -385,8 +418,8 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
                 className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white appearance-none pr-8 cursor-pointer text-xs truncate"
               >
                 {audioDevices.map((device) => (
-                  <option key={device} value={device}>
-                    {device}
+                  <option key={device.name} value={device.name}>
+                    {device.name}
                   </option>
                 ))}
               </select>
@@ -396,6 +429,54 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
             </div>
           </div>
 
+          {/* Microphone Device */}
+          <div className="space-y-2">
+            <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+              Microphone (Your Voice)
+            </label>
+            <div className="relative">
+              <select
+                value={selectedMic}
+                onChange={(e) => handleMicChange(e.target.value)}
+                className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white appearance-none pr-8 cursor-pointer text-xs truncate"
+              >
+                <option value="">None</option>
+                {audioDevices
+                  .filter((device) => device.role !== "loopback")
+                  .map((device) => (
+                    <option key={device.name} value={device.name}>
+                      {device.name}
+                    </option>
+                  ))}
+              </select>
+              <div className="absolute right-3 top-1/2 -translate-y-1/2 pointer-events-none text-white/50">
+                <svg xmlns="http://www.w3.org/2000/svg" width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round"><path d="m6 9 6 6 6-6" /></svg>
+              </div>
+            </div>
+            {selectedMic && (
+              <div className="flex bg-black/40 p-1 rounded border border-white/10 w-fit">
+                <button
+                  onClick={() => handleChange("capture_mix_mode", "mixed")}
+                  className={`px-4 py-1.5 rounded text-xs font-medium transition-all ${formData.capture_mix_mode === "mixed"
+                    ? "bg-blue-600 text-white shadow-lg font-bold"
+                    : "text-gray-400 hover:text-white"
+                    }`}
+                >
+                  Mixed
+                </button>
+                <button
+                  onClick={() => handleChange("capture_mix_mode", "separate")}
+                  className={`px-4 py-1.5 rounded text-xs font-medium transition-all ${formData.capture_mix_mode === "separate"
+                    ? "bg-blue-600 text-white shadow-lg font-bold"
+                    : "text-gray-400 hover:text-white"
+                    }`}
+                >
+                  Separate
+                </button>
+              </div>
+            )}
+          </div>
+
           {/* Transcription Mode */}
           <div className="space-y-2">
             <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
@@ -420,11 +501,22 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
               >
                 Accuracy
               </button>
+              <button
+                onClick={() => handleChange("transcription_mode", "translate")}
+                className={`px-4 py-1.5 rounded text-xs font-medium transition-all ${formData.transcription_mode === "translate"
+                  ? "bg-blue-600 text-white shadow-lg font-bold"
+                  : "text-gray-400 hover:text-white"
+                  }`}
+              >
+                Translate
+              </button>
             </div>
             <p className="text-[10px] text-gray-500">
               {formData.transcription_mode === "accuracy"
                 ? "Uses beam search (5 beams). Highly accurate but slower and uses more CPU."
-                : "Uses greedy decoding. Maximum performance and low latency."}
+                : formData.transcription_mode === "translate"
+                  ? "Translates speech into English. Non-English speech is decoded twice, and the session log keeps the original text."
+                  : "Uses greedy decoding. Maximum performance and low latency."}
             </p>
           </div>
 
@@ -443,6 +535,7 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
                 <option value="zh">Chinese (Mandarin)</option>
                 <option value="pl">Polish</option>
                 <option value="fr">French</option>
+                <option value="auto">Auto-detect</option>
               </select>
               <div className="absolute right-3 top-1/2 -translate-y-1/2 pointer-events-none text-white/50">
                 <svg xmlns="http://www.w3.org/2000/svg" width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round"><path d="m6 9 6 6 6-6" /></svg>
@@ -451,6 +544,74 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
             <p className="text-[10px] text-gray-500">
               The language model will prioritize this language for transcription.
             </p>
+            {formData.whisper_language === "auto" && (
+              <input
+                type="text"
+                value={allowlistText}
+                onChange={(e) => {
+                  setAllowlistText(e.target.value);
+                  setFormData((prev) => ({
+                    ...prev,
+                    whisper_language_allowlist: e.target.value
+                      .split(",")
+                      .map((code) => code.trim())
+                      .filter((code) => code.length > 0),
+                  }));
+                }}
+                placeholder="Allowed languages, e.g. en, pl (empty allows any)"
+                className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white text-xs"
+              />
+            )}
+          </div>
+
+          {/* Whisper Glossary */}
+          <div className="space-y-2">
+            <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+              Glossary
+            </label>
+            <textarea
+              value={glossaryText}
+              onChange={(e) => {
+                setGlossaryText(e.target.value);
+                setFormData((prev) => ({
+                  ...prev,
+                  whisper_glossary: e.target.value
+                    .split("\n")
+                    .map((term) => term.trim())
+                    .filter((term) => term.length > 0),
+                }));
+              }}
+              className="w-full h-24 bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white font-mono text-xs leading-relaxed resize-none custom-scrollbar"
+              placeholder={"Names, products and acronyms, one per line"}
+            />
+            <p className="text-[10px] text-gray-500">
+              Passed to Whisper as a hint, together with the agenda, so these terms are spelled correctly.
+            </p>
+          </div>
+
+          {/* Whisper Blocklist */}
+          <div className="space-y-2">
+            <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+              Phantom Phrases
+            </label>
+            <textarea
+              value={blocklistText}
+              onChange={(e) => {
+                setBlocklistText(e.target.value);
+                setFormData((prev) => ({
+                  ...prev,
+                  whisper_blocklist: e.target.value
+                    .split("\n")
+                    .map((phrase) => phrase.trim())
+                    .filter((phrase) => phrase.length > 0),
+                }));
+              }}
+              className="w-full h-24 bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white font-mono text-xs leading-relaxed resize-none custom-scrollbar"
+              placeholder={"Lines Whisper invents on silence, one per line"}
+            />
+            <p className="text-[10px] text-gray-500">
+              Transcript lines that consist of exactly one of these phrases are dropped. Repeated phrases and low-confidence lines are always filtered.
+            </p>
           </div>
 
           {/* Detection Settings */}
@@ -557,6 +718,25 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
                 </div>
               </div>
             </div>
+
+            <div className="grid grid-cols-2 gap-4">
+              <div className="space-y-2">
+                <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+                  Agenda Batch Size
+                </label>
+                <input
+                  type="number"
+                  min="1"
+                  step="1"
+                  value={formData.agenda_batch_size || 8}
+                  onChange={(e) =>
+                    handleChange("agenda_batch_size", parseInt(e.target.value) || 1)
+                  }
+                  title="Most agenda items scored in one Ollama prompt. 1 scores each item on its own."
+                  className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white"
+                />
+              </div>
+            </div>
           </div>
 
           {/* Global Hotkey */}
@@ -594,21 +774,84 @@ export function SettingsView({ config, defaultMode, onDefaultModeChange, onSave,
             </div>
           </div>
 
-          {/* Whisper Path */}
+          {/* Transcription Backend */}
           <div className="space-y-2">
             <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
-              Whisper GGML Path
+              Transcription Backend
             </label>
-            <input
-              type="text"
-              value={formData.whisper_ggml_path || ""}
-              onChange={(e) => handleChange("whisper_ggml_path", e.target.value)}
-              className={`w-full bg-black/40 border rounded px-3 py-2 focus:outline-none transition-colors text-white/50 text-xs truncate ${pathValidation === 'invalid' ? 'border-red-500/50 focus:border-red-500' : 'border-white/10 focus:border-blue-500'
-                }`}
-            />
-            {pathValidation === 'invalid' && <p className="text-[10px] text-red-500">File not found - Not Saved</p>}
+            <div className="flex bg-black/40 rounded p-1 border border-white/10 w-fit">
+              <button
+                onClick={() => handleChange("transcription_backend", "local")}
+                className={`px-4 py-1.5 rounded text-xs font-medium transition-all ${formData.transcription_backend !== "http"
+                  ? "bg-blue-600 text-white shadow-lg font-bold"
+                  : "text-gray-400 hover:text-white"
+                  }`}
+              >
+                Local
+              </button>
+              <button
+                onClick={() => handleChange("transcription_backend", "http")}
+                className={`px-4 py-1.5 rounded text-xs font-medium transition-all ${formData.transcription_backend === "http"
+                  ? "bg-blue-600 text-white shadow-lg font-bold"
+                  : "text-gray-400 hover:text-white"
+                  }`}
+              >
+                Server
+              </button>
+            </div>
+            <p className="text-[10px] text-gray-500">
+              {formData.transcription_backend === "http"
+                ? "Sends speech to a whisper.cpp server or an OpenAI-compatible transcription endpoint."
+                : "Runs the Whisper model on this machine."}
+            </p>
           </div>
 
+          {formData.transcription_backend === "http" ? (
+            <div className="space-y-2">
+              <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+                Transcription Server
+              </label>
+              <input
+                type="text"
+                value={formData.transcription_url || ""}
+                onChange={(e) => handleChange("transcription_url", e.target.value)}
+                placeholder="http://192.168.1.20:8080/inference"
+                className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white text-xs"
+              />
+              <div className="grid grid-cols-2 gap-4">
+                <input
+                  type="password"
+                  value={formData.transcription_api_key || ""}
+                  onChange={(e) => handleChange("transcription_api_key", e.target.value)}
+                  placeholder="API key (optional)"
+                  className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white text-xs"
+                />
+                <input
+                  type="text"
+                  value={formData.transcription_model || ""}
+                  onChange={(e) => handleChange("transcription_model", e.target.value)}
+                  placeholder="Model (optional), e.g. whisper-1"
+                  className="w-full bg-black/40 border border-white/10 rounded px-3 py-2 focus:outline-none focus:border-blue-500 transition-colors text-white text-xs"
+                />
+              </div>
+            </div>
+          ) : (
+            /* Whisper Path */
+            <div className="space-y-2">
+              <label className="block text-xs font-semibold uppercase tracking-wider text-gray-500">
+                Whisper GGML Path
+              </label>
+              <input
+                type="text"
+                value={formData.whisper_ggml_path || ""}
+                onChange={(e) => handleChange("whisper_ggml_path", e.target.value)}
+                className={`w-full bg-black/40 border rounded px-3 py-2 focus:outline-none transition-colors text-white/50 text-xs truncate ${pathValidation === 'invalid' ? 'border-red-500/50 focus:border-red-500' : 'border-white/10 focus:border-blue-500'
+                  }`}
+              />
+              {pathValidation === 'invalid' && <p className="text-[10px] text-red-500">File not found - Not Saved</p>}
+            </div>
+          )}
+
         </div>
       </div>
     </div >
@@ -0,0 +1,20 @@
+export type AudioRole = "loopback" | "mic";
+
+/** One transcript line; times are wall-clock milliseconds since the Unix epoch. */
+export interface TranscriptSegment {
+    start_ms: number;
+    end_ms: number;
+    role: AudioRole | null;
+    text: string;
+    /** What was actually said, when `text` is an English translation of it. */
+    original_text: string | null;
+    avg_token_prob: number;
+    no_speech_prob: number | null;
+    language: string | null;
+    language_prob: number | null;
+}
+
+export interface Transcript {
+    text: string;
+    segments: TranscriptSegment[];
+}